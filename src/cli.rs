@@ -0,0 +1,275 @@
+use gstreamer as gst;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::PathBuf;
+use videotest::RtspTransport;
+
+/// Help text printed for `--help` and after usage errors
+pub const USAGE: &str = "\
+Usage: videotest [OPTIONS] [FILE|URI]...
+
+Plays the given files and URIs one after another.
+
+Options:
+  -s, --start <TIME>     Start playing at TIME, as seconds, MM:SS or HH:MM:SS
+  -v, --volume <0-100>   Set the volume in percent
+  -m, --mute             Start with audio muted
+  -f, --fullscreen       Start in fullscreen mode
+  -l, --loop             Loop the file, or the whole playlist when given several
+  -r, --rate <RATE>      Set the playback rate, e.g. 0.5 or 2 (negative plays backwards)
+      --sub-file <FILE>  Show subtitles from FILE with the first media
+      --rtsp-transport <tcp|udp>
+                         Receive RTSP streams over TCP or UDP only
+      --rtsp-latency <MS>
+                         Set the RTSP jitter buffer size in milliseconds
+  -x, --exit-on-eos      Quit when playback reaches the end
+  -h, --help             Print this help
+      --                 Treat all following arguments as files";
+
+/// Why the command line could not be turned into options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    Help,          // --help was given, the caller prints the usage and exits
+    Usage(String), // The arguments are invalid
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::Usage(message) => write!(f, "error: {}\n\n{}", message, USAGE),
+        }
+    }
+}
+
+/// Startup options given on the command line
+#[derive(Debug, Clone, Default)]
+pub struct CliOptions {
+    pub media: Vec<OsString>,          // Files and URIs to play, in order
+    pub start: Option<gst::ClockTime>, // Position the first media starts at
+    pub volume: Option<f64>,           // Volume from 0.0 to 1.0
+    pub mute: bool,                    // Start with audio muted
+    pub fullscreen: bool,              // Open the window in fullscreen mode
+    pub looping: bool,                 // Loop the file or playlist
+    pub rate: Option<f64>,             // Playback rate, 1.0 is normal speed
+    pub subtitle: Option<PathBuf>,     // External subtitle file for the first media
+    pub rtsp_transport: Option<RtspTransport>, // Transport for RTSP streams
+    pub rtsp_latency: Option<gst::ClockTime>, // Jitter buffer size for RTSP streams
+    pub exit_on_eos: bool,             // Quit once nothing is left to play
+}
+
+impl CliOptions {
+    /// Parses the arguments after the program name. Options may be given as
+    /// `--name value` or `--name=value`.
+    pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Self, CliError> {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter();
+        let mut only_media = false;
+        while let Some(arg) = args.next() {
+            let text = arg.to_string_lossy();
+            if only_media || !text.starts_with('-') || text == "-" {
+                options.media.push(arg);
+                continue;
+            }
+
+            let (name, inline_value) = match text.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_string(), Some(OsString::from(value)))
+                }
+                _ => (text.to_string(), None),
+            };
+            let mut value = |what: &str| -> Result<OsString, CliError> {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError::Usage(format!("option '{}' needs {}", name, what)))
+            };
+
+            match name.as_str() {
+                "--" => only_media = true,
+                "-h" | "--help" => return Err(CliError::Help),
+                "-s" | "--start" => {
+                    let time = value("a time")?;
+                    let time = time.to_string_lossy();
+                    options.start = Some(parse_time(&time).ok_or_else(|| {
+                        CliError::Usage(format!("invalid start position '{}'", time))
+                    })?);
+                }
+                "-v" | "--volume" => {
+                    let volume = value("a volume")?;
+                    let volume = volume.to_string_lossy();
+                    let percent = volume
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|percent| (0.0..=100.0).contains(percent))
+                        .ok_or_else(|| {
+                            CliError::Usage(format!(
+                                "invalid volume '{}', expected 0 to 100",
+                                volume
+                            ))
+                        })?;
+                    options.volume = Some(percent / 100.0);
+                }
+                "-m" | "--mute" => options.mute = true,
+                "-f" | "--fullscreen" => options.fullscreen = true,
+                "-l" | "--loop" => options.looping = true,
+                "-r" | "--rate" => {
+                    let rate = value("a rate")?;
+                    let rate = rate.to_string_lossy();
+                    options.rate = Some(
+                        rate.parse::<f64>()
+                            .ok()
+                            .filter(|rate| rate.is_finite() && *rate != 0.0)
+                            .ok_or_else(|| {
+                                CliError::Usage(format!("invalid playback rate '{}'", rate))
+                            })?,
+                    );
+                }
+                "--sub-file" => options.subtitle = Some(PathBuf::from(value("a file")?)),
+                "--rtsp-transport" => {
+                    let transport = value("a transport")?;
+                    let transport = transport.to_string_lossy();
+                    options.rtsp_transport = Some(match transport.to_ascii_lowercase().as_str() {
+                        "tcp" => RtspTransport::Tcp,
+                        "udp" => RtspTransport::Udp,
+                        _ => {
+                            return Err(CliError::Usage(format!(
+                                "invalid RTSP transport '{}', expected tcp or udp",
+                                transport
+                            )))
+                        }
+                    });
+                }
+                "--rtsp-latency" => {
+                    let latency = value("a latency")?;
+                    let latency = latency.to_string_lossy();
+                    options.rtsp_latency = Some(
+                        latency
+                            .parse::<u64>()
+                            .map(gst::ClockTime::from_mseconds)
+                            .map_err(|_| {
+                                CliError::Usage(format!("invalid RTSP latency '{}'", latency))
+                            })?,
+                    );
+                }
+                "-x" | "--exit-on-eos" => options.exit_on_eos = true,
+                _ => return Err(CliError::Usage(format!("unknown option '{}'", name))),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Parses a time given as seconds, MM:SS or HH:MM:SS. Seconds may have a fraction.
+fn parse_time(text: &str) -> Option<gst::ClockTime> {
+    let parts: Vec<&str> = text.split(':').collect();
+    let (seconds, whole) = parts.split_last()?;
+    if whole.len() > 2 {
+        return None;
+    }
+    let mut total = seconds.parse::<f64>().ok().filter(|s| *s >= 0.0)?;
+    for (part, unit) in whole.iter().rev().zip([60.0, 3600.0]) {
+        total += part.parse::<u64>().ok()? as f64 * unit;
+    }
+    if !total.is_finite() {
+        return None;
+    }
+    Some(gst::ClockTime::from_nseconds((total * 1e9) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliOptions, CliError> {
+        CliOptions::parse(args.iter().map(OsString::from))
+    }
+
+    /// Parses arguments that must be rejected and gets the message
+    fn usage_error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(CliError::Usage(message)) => message,
+            other => panic!("{:?} was not rejected: {:?}", args, other),
+        }
+    }
+
+    #[test]
+    fn values_follow_the_option_or_an_equals_sign() {
+        let options = parse(&[
+            "--volume=40",
+            "-s",
+            "1:30",
+            "--rate",
+            "-0.5",
+            "--rtsp-transport=TCP",
+            "--rtsp-latency",
+            "200",
+            "-m",
+            "a.mp4",
+            "--loop",
+            "b.mp4",
+        ])
+        .unwrap();
+        assert_eq!(options.volume, Some(0.4));
+        assert_eq!(options.start, Some(gst::ClockTime::from_seconds(90)));
+        assert_eq!(options.rate, Some(-0.5));
+        assert_eq!(options.rtsp_transport, Some(RtspTransport::Tcp));
+        assert_eq!(
+            options.rtsp_latency,
+            Some(gst::ClockTime::from_mseconds(200))
+        );
+        assert!(options.mute && options.looping && !options.fullscreen);
+        assert_eq!(options.media, ["a.mp4", "b.mp4"]);
+    }
+
+    #[test]
+    fn arguments_after_double_dash_are_media() {
+        let options = parse(&["-f", "--", "-file.mp4", "--help", "-"]).unwrap();
+        assert!(options.fullscreen);
+        assert_eq!(options.media, ["-file.mp4", "--help", "-"]);
+    }
+
+    #[test]
+    fn help_is_not_an_error() {
+        assert_eq!(parse(&["a.mp4", "-h"]).unwrap_err(), CliError::Help);
+        assert_eq!(parse(&["--help"]).unwrap_err().to_string(), USAGE);
+    }
+
+    #[test]
+    fn invalid_arguments_are_usage_errors() {
+        assert_eq!(usage_error(&["--bogus"]), "unknown option '--bogus'");
+        assert_eq!(usage_error(&["--bogus=1"]), "unknown option '--bogus'");
+        assert_eq!(usage_error(&["--start"]), "option '--start' needs a time");
+        assert_eq!(usage_error(&["-v"]), "option '-v' needs a volume");
+        assert!(usage_error(&["--volume", "101"]).contains("expected 0 to 100"));
+        assert!(usage_error(&["--volume=-1"]).contains("expected 0 to 100"));
+        assert_eq!(usage_error(&["--rate", "0"]), "invalid playback rate '0'");
+        assert_eq!(usage_error(&["--rate=inf"]), "invalid playback rate 'inf'");
+        assert!(usage_error(&["--rtsp-transport", "http"]).contains("expected tcp or udp"));
+        assert_eq!(
+            usage_error(&["-s", "1:2:3:4"]),
+            "invalid start position '1:2:3:4'"
+        );
+
+        // The usage is printed after the message
+        let error = parse(&["--bogus"]).unwrap_err().to_string();
+        assert!(error.starts_with("error: unknown option '--bogus'\n\n"));
+        assert!(error.ends_with(USAGE));
+    }
+
+    #[test]
+    fn times_are_seconds_minutes_or_hours() {
+        let seconds = |s: f64| Some(gst::ClockTime::from_nseconds((s * 1e9) as u64));
+        assert_eq!(parse_time("45"), seconds(45.0));
+        assert_eq!(parse_time("2:05"), seconds(125.0));
+        assert_eq!(parse_time("1:02:03"), seconds(3723.0));
+        assert_eq!(parse_time("1.5"), seconds(1.5));
+        assert_eq!(parse_time("0:00:07.25"), seconds(7.25));
+        assert_eq!(parse_time("1:2:3:4"), None);
+        assert_eq!(parse_time("-5"), None);
+        assert_eq!(parse_time("1:-5"), None);
+        assert_eq!(parse_time("1.5:00"), None);
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("ten"), None);
+    }
+}
@@ -0,0 +1,275 @@
+use gstreamer as gst;
+use std::fmt;
+
+use crate::location::LocationError;
+
+/// Context attached to errors that originate from the GStreamer pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorDetails {
+    pub message: String,        // Human readable error message
+    pub source: Option<String>, // Path of the element that posted the error
+    pub debug: Option<String>,  // GStreamer debug string with internal details
+}
+
+impl ErrorDetails {
+    /// Creates details carrying only a message
+    pub fn new(message: impl Into<String>) -> Self {
+        ErrorDetails {
+            message: message.into(),
+            source: None,
+            debug: None,
+        }
+    }
+}
+
+/// Custom error type for the video player that can be safely sent between threads
+#[derive(Debug, Clone)]
+pub enum PlayerError {
+    GstreamerError(String),
+    InitializationError(String),
+    NotFound(ErrorDetails),           // The media does not exist
+    PermissionDenied(ErrorDetails),   // The media exists but may not be read
+    UnsupportedFormat(ErrorDetails),  // The container or stream type is not understood
+    MissingCodec(ErrorDetails),       // A decoder or other plugin is not installed
+    DecodeFailure(ErrorDetails),      // The stream is corrupt or could not be decoded
+    NetworkFailure(ErrorDetails),     // A network source failed to connect or read
+    StateChangeFailure(ErrorDetails), // The pipeline refused to change state
+    Pipeline(ErrorDetails),           // Any other error posted on the bus
+    PlaylistFile(ErrorDetails),       // A playlist file could not be read or written
+    InvalidLocation(LocationError),   // A path or URI cannot be played from
+}
+
+impl PlayerError {
+    /// Classifies an error posted on the bus by its GStreamer error domain and code.
+    /// `network` tells whether the current media comes from a network source.
+    pub fn from_glib(
+        error: &glib::Error,
+        source: Option<String>,
+        debug: Option<String>,
+        network: bool,
+    ) -> Self {
+        let details = ErrorDetails {
+            message: error.message().to_string(),
+            source,
+            debug,
+        };
+
+        if let Some(code) = error.kind::<gst::ResourceError>() {
+            let permission_denied = details
+                .debug
+                .as_deref()
+                .is_some_and(|debug| debug.contains("Permission denied"));
+            // Servers answering 404 make some sources fail to open rather than report NotFound
+            let not_found = details.message.contains("Not Found")
+                || details
+                    .debug
+                    .as_deref()
+                    .is_some_and(|debug| debug.contains("Not Found") || debug.contains("(404)"));
+            // Hard failures come first so that a missing stream is not retried as a network error
+            return match code {
+                gst::ResourceError::NotAuthorized => PlayerError::PermissionDenied(details),
+                gst::ResourceError::OpenRead if permission_denied => {
+                    PlayerError::PermissionDenied(details)
+                }
+                gst::ResourceError::NotFound => PlayerError::NotFound(details),
+                gst::ResourceError::OpenRead | gst::ResourceError::Read if not_found => {
+                    PlayerError::NotFound(details)
+                }
+                _ if network => PlayerError::NetworkFailure(details),
+                _ => PlayerError::Pipeline(details),
+            };
+        }
+
+        if let Some(code) = error.kind::<gst::StreamError>() {
+            return match code {
+                gst::StreamError::CodecNotFound => PlayerError::MissingCodec(details),
+                gst::StreamError::TypeNotFound
+                | gst::StreamError::WrongType
+                | gst::StreamError::NotImplemented
+                | gst::StreamError::Demux
+                | gst::StreamError::Format => PlayerError::UnsupportedFormat(details),
+                gst::StreamError::Decode
+                | gst::StreamError::Decrypt
+                | gst::StreamError::DecryptNoKey => PlayerError::DecodeFailure(details),
+                _ => PlayerError::Pipeline(details),
+            };
+        }
+
+        if let Some(code) = error.kind::<gst::CoreError>() {
+            return match code {
+                gst::CoreError::MissingPlugin => PlayerError::MissingCodec(details),
+                gst::CoreError::StateChange => PlayerError::StateChangeFailure(details),
+                _ => PlayerError::Pipeline(details),
+            };
+        }
+
+        PlayerError::Pipeline(details)
+    }
+
+    /// Creates an error for a state change the pipeline refused
+    pub(crate) fn state_change(message: impl Into<String>) -> Self {
+        PlayerError::StateChangeFailure(ErrorDetails::new(message))
+    }
+
+    /// Gets the pipeline context of the error, if it came from the pipeline
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            PlayerError::GstreamerError(_)
+            | PlayerError::InitializationError(_)
+            | PlayerError::InvalidLocation(_) => None,
+            PlayerError::NotFound(details)
+            | PlayerError::PermissionDenied(details)
+            | PlayerError::UnsupportedFormat(details)
+            | PlayerError::MissingCodec(details)
+            | PlayerError::DecodeFailure(details)
+            | PlayerError::NetworkFailure(details)
+            | PlayerError::StateChangeFailure(details)
+            | PlayerError::Pipeline(details)
+            | PlayerError::PlaylistFile(details) => Some(details),
+        }
+    }
+}
+
+impl From<LocationError> for PlayerError {
+    fn from(error: LocationError) -> Self {
+        PlayerError::InvalidLocation(error)
+    }
+}
+
+impl std::error::Error for PlayerError {}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerError::GstreamerError(msg) => write!(f, "GStreamer error: {}", msg),
+            PlayerError::InitializationError(msg) => write!(f, "Initialization error: {}", msg),
+            PlayerError::NotFound(details) => write!(f, "Not found: {}", details.message),
+            PlayerError::PermissionDenied(details) => {
+                write!(f, "Permission denied: {}", details.message)
+            }
+            PlayerError::UnsupportedFormat(details) => {
+                write!(f, "Unsupported format: {}", details.message)
+            }
+            PlayerError::MissingCodec(details) => write!(f, "Missing codec: {}", details.message),
+            PlayerError::DecodeFailure(details) => {
+                write!(f, "Decoding failed: {}", details.message)
+            }
+            PlayerError::NetworkFailure(details) => {
+                write!(f, "Network error: {}", details.message)
+            }
+            PlayerError::StateChangeFailure(details) => {
+                write!(f, "State change failed: {}", details.message)
+            }
+            PlayerError::Pipeline(details) => write!(f, "Pipeline error: {}", details.message),
+            PlayerError::PlaylistFile(details) => {
+                write!(f, "Playlist error: {}", details.message)
+            }
+            PlayerError::InvalidLocation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Classifies an error with the given debug string
+    fn classify(
+        error: impl glib::error::ErrorDomain,
+        message: &str,
+        debug: Option<&str>,
+        network: bool,
+    ) -> PlayerError {
+        PlayerError::from_glib(
+            &glib::Error::new(error, message),
+            Some("/GstPlayBin:playbin/GstURIDecodeBin:uridecodebin0/GstSoupHTTPSrc:source".into()),
+            debug.map(str::to_string),
+            network,
+        )
+    }
+
+    #[test]
+    fn missing_media_is_not_a_network_failure() {
+        let error = classify(gst::ResourceError::NotFound, "Not Found", None, true);
+        assert!(matches!(error, PlayerError::NotFound(_)));
+        let error = classify(
+            gst::ResourceError::OpenRead,
+            "Could not open resource for reading.",
+            Some("Not Found (404), URL: http://example.com/missing.mp4"),
+            true,
+        );
+        assert!(matches!(error, PlayerError::NotFound(_)));
+        let error = classify(gst::ResourceError::NotFound, "No such file", None, false);
+        assert!(matches!(error, PlayerError::NotFound(_)));
+    }
+
+    #[test]
+    fn other_resource_errors_of_network_media_are_network_failures() {
+        for code in [
+            gst::ResourceError::OpenRead,
+            gst::ResourceError::Read,
+            gst::ResourceError::Failed,
+        ] {
+            let error = classify(code, "Could not connect", Some("Connection refused"), true);
+            assert!(
+                matches!(error, PlayerError::NetworkFailure(_)),
+                "{:?}",
+                code
+            );
+        }
+        let error = classify(gst::ResourceError::Read, "Could not read", None, false);
+        assert!(matches!(error, PlayerError::Pipeline(_)));
+    }
+
+    #[test]
+    fn refused_access_is_permission_denied() {
+        let error = classify(
+            gst::ResourceError::OpenRead,
+            "Could not open file for reading.",
+            Some("system error: Permission denied"),
+            false,
+        );
+        assert!(matches!(error, PlayerError::PermissionDenied(_)));
+        let error = classify(
+            gst::ResourceError::NotAuthorized,
+            "Unauthorized",
+            None,
+            true,
+        );
+        assert!(matches!(error, PlayerError::PermissionDenied(_)));
+    }
+
+    #[test]
+    fn stream_and_core_errors() {
+        let cases = [
+            (
+                classify(gst::StreamError::CodecNotFound, "No decoder", None, false),
+                "MissingCodec",
+            ),
+            (
+                classify(gst::StreamError::Decode, "Corrupt frame", None, true),
+                "DecodeFailure",
+            ),
+            (
+                classify(gst::CoreError::StateChange, "Refused", None, false),
+                "StateChangeFailure",
+            ),
+            (
+                classify(gst::CoreError::MissingPlugin, "No element", None, false),
+                "MissingCodec",
+            ),
+        ];
+        for (error, expected) in cases {
+            assert!(format!("{:?}", error).starts_with(expected), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn details_are_kept() {
+        let error = classify(gst::ResourceError::Read, "Read failed", Some("EOF"), true);
+        let details = error.details().unwrap();
+        assert_eq!(details.message, "Read failed");
+        assert_eq!(details.debug.as_deref(), Some("EOF"));
+        assert!(details.source.as_deref().unwrap().ends_with("source"));
+    }
+}
@@ -0,0 +1,42 @@
+use gstreamer as gst;
+
+use crate::error::PlayerError;
+use crate::missing_plugin::MissingPlugin;
+
+/// Something that happened in the pipeline, reported to the host application.
+///
+/// Events are collected from the GStreamer bus by [`crate::MediaPlayer::update`]
+/// and handed out by [`crate::MediaPlayer::poll_events`].
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// The pipeline moved from one state to another
+    StateChanged {
+        old: gst::State,
+        current: gst::State,
+        pending: gst::State,
+    },
+    /// The end of the stream was reached
+    EndOfStream,
+    /// Playback continued gaplessly with the queued media, carrying its URI
+    MediaChanged(String),
+    /// An element posted an error, playback has most likely stopped
+    Error(PlayerError),
+    /// An element posted a warning, playback continues
+    Warning {
+        source: Option<String>, // Path of the element that posted the message
+        message: String,
+        debug: Option<String>,
+    },
+    /// A network stream failed and is being opened again, as allowed by the retry policy
+    Reconnecting { attempt: u32, max_attempts: u32 },
+    /// Buffering progress in percent (0 to 100)
+    Buffering(i32),
+    /// Metadata found in the stream (title, artist, codecs, ...)
+    Tags(gst::TagList),
+    /// The duration of the media changed and has to be queried again
+    DurationChanged,
+    /// A plugin needed to play the media is not installed
+    MissingPlugin(MissingPlugin),
+    /// The set of available streams (audio, video, subtitle tracks) changed
+    StreamCollection(gst::StreamCollection),
+}
@@ -0,0 +1,33 @@
+//! GStreamer backed video playback for egui.
+//!
+//! [`MediaPlayer`] owns the GStreamer pipeline, [`VideoPlayer`] wraps it into an
+//! embeddable egui widget that draws the video and, optionally, a control bar.
+
+mod error;
+mod events;
+mod location;
+mod missing_plugin;
+mod notifications;
+mod player;
+mod playlist;
+mod playlist_file;
+mod retry;
+mod rtsp;
+mod timeshift;
+mod widget;
+
+pub use error::{ErrorDetails, PlayerError};
+pub use events::PlayerEvent;
+pub use location::{LocationError, MediaLocation};
+pub use missing_plugin::{MissingPlugin, PluginSet};
+pub use notifications::{Notification, NotificationLevel, Notifications};
+pub use player::{
+    EosAction, MediaPlayer, ReverseSupport, SeekMode, VideoFrame, MAX_RATE, MIN_RATE,
+    PLAYBACK_RATES,
+};
+pub use playlist::{Playlist, PlaylistItem, RepeatMode};
+pub use playlist_file::PlaylistFormat;
+pub use retry::RetryPolicy;
+pub use rtsp::{RtspSettings, RtspTransport};
+pub use timeshift::TimeShift;
+pub use widget::{AspectRatio, FitMode, VideoPlayer, VideoPlayerOptions};
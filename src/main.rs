@@ -1,318 +1,354 @@
+mod cli;
+
+use cli::{CliError, CliOptions};
 use eframe::egui;
-use egui::{TextureHandle, ViewportBuilder};
-use glib::{self, MainContext};
+use egui::ViewportBuilder;
 use gstreamer as gst;
-use gstreamer::bus::BusWatchGuard;
-use gstreamer::prelude::*;
-use gstreamer_app as gst_app;
-use gstreamer_video as gst_video;
 use rfd::FileDialog;
-use std::fmt;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::cmp::Ordering;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use videotest::{
+    AspectRatio, EosAction, FitMode, MediaLocation, Notification, NotificationLevel, Notifications,
+    PlayerError, PlayerEvent, Playlist, PlaylistFormat, RepeatMode, RtspSettings, RtspTransport,
+    SeekMode, TimeShift, VideoPlayer, VideoPlayerOptions,
+};
+
+/// File extensions offered by the open dialogs
+const VIDEO_EXTENSIONS: [&str; 5] = ["mp4", "webm", "mkv", "avi", "mov"];
+
+/// Actions the user can retry from an error notification
+#[derive(Debug, Clone)]
+enum RetryAction {
+    Open(MediaLocation), // Open the given file or stream again
+    TogglePlayback,      // Try to play or pause again
+}
 
-/// Custom error type for the video player that can be safely sent between threads
-#[derive(Debug)]
-enum PlayerError {
-    GstreamerError(String),
-    InitializationError(String),
+/// The desktop application: a menu bar around a full window [`VideoPlayer`]
+struct PlayerApp {
+    video: VideoPlayer,                        // The embedded video player widget
+    notifications: Notifications<RetryAction>, // Toasts drawn over the video
+    current_media: Option<MediaLocation>,      // The media that was opened last
+    playlist: Playlist,                        // Media played one after another
+    show_playlist: bool,                       // Whether the playlist side panel is open
+    url_input: Option<String>,                 // Text of the "Open URL" dialog while it is open
+    exit_on_eos: bool,                         // Quit once nothing is left to play
+    start_position: Option<gst::ClockTime>,    // Seek target applied once media has prerolled
+    start_rate: Option<f64>,                   // Rate applied once media has prerolled
 }
 
-impl std::error::Error for PlayerError {}
+impl PlayerApp {
+    /// Creates the application with a fresh video player, opening the media
+    /// given on the command line
+    fn new(cli: CliOptions) -> Result<Self, PlayerError> {
+        let mut app = PlayerApp {
+            video: VideoPlayer::with_options(VideoPlayerOptions {
+                eos_action: EosAction::Next,
+                muted: cli.mute,
+                ..Default::default()
+            })?,
+            notifications: Notifications::new(),
+            current_media: None,
+            playlist: Playlist::new(),
+            show_playlist: false,
+            url_input: None,
+            exit_on_eos: cli.exit_on_eos,
+            start_position: cli.start,
+            start_rate: cli.rate,
+        };
 
-impl fmt::Display for PlayerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            PlayerError::GstreamerError(msg) => write!(f, "GStreamer error: {}", msg),
-            PlayerError::InitializationError(msg) => write!(f, "Initialization error: {}", msg),
+        let player = app.video.player_mut();
+        if let Some(volume) = cli.volume {
+            player.set_volume(volume);
+        }
+        let mut rtsp = player.rtsp_settings();
+        rtsp.transport = cli.rtsp_transport.unwrap_or(rtsp.transport);
+        rtsp.latency = cli.rtsp_latency.unwrap_or(rtsp.latency);
+        player.set_rtsp_settings(rtsp);
+        if let Err(e) = player.set_subtitle_file(cli.subtitle.as_deref()) {
+            app.notify_error(&e, None);
         }
+        app.open_args(cli.media);
+
+        // A single file loops by itself, several loop as a playlist
+        if cli.looping {
+            if app.playlist.len() > 1 {
+                app.playlist.set_repeat(RepeatMode::All);
+            } else if let Err(e) = app.video.set_eos_action(EosAction::Loop) {
+                app.notify_error(&e, None);
+            }
+        }
+        Ok(app)
     }
-}
 
-/// Represents a single frame of video data
-struct VideoFrame {
-    width: i32,
-    height: i32,
-    data: Vec<u8>, // RGBA pixel data
-}
+    /// Opens the files and URIs given on the command line, relative paths are
+    /// resolved against the current directory
+    fn open_args(&mut self, media: Vec<OsString>) {
+        let mut locations = Vec::new();
+        for arg in media {
+            match MediaLocation::from_arg(&arg) {
+                Ok(location) => locations.push(location),
+                Err(e) => self.notify_error(&e.into(), None),
+            }
+        }
 
-/// The main media player structure that handles both GStreamer pipeline and UI state
-struct MediaPlayer {
-    pipeline: gst::Element,                      // The GStreamer playbin pipeline
-    _appsink: gst_app::AppSink,                  // Sink element that receives video frames
-    duration: Option<gst::ClockTime>,            // Total duration of the current media
-    position: Option<gst::ClockTime>,            // Current playback position
-    video_frame: Arc<Mutex<Option<VideoFrame>>>, // Current video frame data
-    texture: Option<TextureHandle>,              // Egui texture for displaying the video
-    _bus_watch: BusWatchGuard,                   // Watch for GStreamer bus messages
-    main_context: glib::MainContext,             // GLib main context for event processing
-    volume: f64,                                 // Playback volume (0.0 to 1.0)
-}
+        match locations.len() {
+            0 => (),
+            1 => self.open(locations.remove(0)),
+            _ => self.enqueue(locations),
+        }
+    }
 
-impl MediaPlayer {
-    /// Creates a new MediaPlayer instance, initializing the GStreamer pipeline
-    /// and setting up video processing
-    fn new() -> Result<Self, PlayerError> {
-        // Initialize GStreamer
-        gst::init().map_err(|e| PlayerError::InitializationError(e.to_string()))?;
-
-        // Create playbin element (an all-in-one media player pipeline)
-        let pipeline = gst::ElementFactory::make("playbin")
-            .name("playbin")
-            .build()
-            .map_err(|e| PlayerError::GstreamerError(format!("Failed to create playbin: {}", e)))?;
-
-        // Create appsink for receiving video frames
-        // Configure it to receive RGBA video for easy display in Egui
-        let appsink = gst_app::AppSink::builder()
-            .name("videosink")
-            .caps(
-                &gst::Caps::builder("video/x-raw")
-                    .field("format", "RGBA")
-                    .build(),
-            )
-            .build();
-
-        // Create a bin for video conversion
-        // This ensures we get the video format we want (RGBA)
-        let video_bin = gst::Bin::new();
-        let videoconvert = gst::ElementFactory::make("videoconvert")
-            .build()
-            .map_err(|e| PlayerError::GstreamerError(e.to_string()))?;
-
-        video_bin.add(&videoconvert).unwrap();
-        video_bin.add(appsink.upcast_ref::<gst::Element>()).unwrap();
-
-        // Link the converter to the sink
-        videoconvert
-            .link(appsink.upcast_ref::<gst::Element>())
-            .unwrap();
-
-        // Create a ghost pad to expose the videoconvert sink pad on the bin
-        let sink_pad = videoconvert.static_pad("sink").unwrap();
-        let ghost_pad = gst::GhostPad::builder_with_target(&sink_pad)
-            .unwrap()
-            .name("sink")
-            .build();
-        video_bin.add_pad(&ghost_pad).unwrap();
-
-        // Configure the pipeline to use our video processing bin
-        pipeline.set_property("video-sink", &video_bin);
-
-        // Set up shared storage for video frames
-        let video_frame = Arc::new(Mutex::new(None));
-        let video_frame_clone = Arc::clone(&video_frame);
-
-        // Configure the appsink to handle incoming video frames
-        appsink.set_callbacks(
-            gst_app::AppSinkCallbacks::builder()
-                .new_sample(move |appsink| {
-                    let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Error)?;
-                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
-                    let caps = sample.caps().ok_or(gst::FlowError::Error)?;
-
-                    // Get video dimensions from the caps
-                    let video_info =
-                        gst_video::VideoInfo::from_caps(caps).map_err(|_| gst::FlowError::Error)?;
-                    let width = video_info.width() as i32;
-                    let height = video_info.height() as i32;
-
-                    // Copy frame data
-                    let mapped_buffer = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
-                    let data = mapped_buffer.to_vec();
-
-                    // Store the frame for later display
-                    *video_frame_clone.lock().unwrap() = Some(VideoFrame {
-                        width,
-                        height,
-                        data,
-                    });
+    /// Turns paths picked or dropped by the user into media locations, reporting
+    /// the ones that cannot be played from
+    fn locations(&mut self, paths: Vec<PathBuf>) -> Vec<MediaLocation> {
+        let mut locations = Vec::new();
+        for path in paths {
+            match MediaLocation::from_path(path) {
+                Ok(location) => locations.push(location),
+                Err(e) => self.notify_error(&e.into(), None),
+            }
+        }
+        locations
+    }
 
-                    Ok(gst::FlowSuccess::Ok)
-                })
-                .build(),
-        );
-
-        // Set up bus watch to handle pipeline messages
-        let pipeline_weak = pipeline.downgrade();
-        let bus = pipeline.bus().unwrap();
-        let bus_watch = bus
-            .add_watch(move |_, msg| {
-                if let Some(pipeline) = pipeline_weak.upgrade() {
-                    match msg.view() {
-                        gst::MessageView::Error(err) => {
-                            eprintln!(
-                                "Error from {:?}: {} ({:?})",
-                                err.src().map(|s| s.path_string()),
-                                err.error(),
-                                err.debug()
-                            );
-                        }
-                        gst::MessageView::Eos(_) => {
-                            println!("End of stream reached");
-                            let _ = pipeline.set_state(gst::State::Ready);
-                        }
-                        gst::MessageView::StateChanged(state) => {
-                            // Only print state changes of the pipeline
-                            if state
-                                .src()
-                                .map(|s| {
-                                    s.as_ptr() == pipeline.upcast_ref::<gst::Object>().as_ptr()
-                                })
-                                .unwrap_or(false)
-                            {
-                                println!(
-                                    "Pipeline state changed: {:?} -> {:?}",
-                                    state.old(),
-                                    state.current()
-                                );
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-                glib::ControlFlow::Continue
-            })
-            .expect("Failed to add bus watch");
-
-        Ok(MediaPlayer {
-            pipeline,
-            _appsink: appsink,
-            duration: None,
-            position: None,
-            video_frame,
-            texture: None,
-            _bus_watch: bus_watch,
-            main_context: MainContext::default(),
-            volume: 1.0,
-        })
+    /// Applies the start position and rate from the command line once media can be seeked
+    fn apply_start_options(&mut self) {
+        if let Some(start) = self.start_position.take() {
+            if let Err(e) = self.video.player_mut().seek_to(start, SeekMode::Accurate) {
+                self.notify_error(&e, None);
+            }
+        }
+        if let Some(rate) = self.start_rate.take() {
+            if let Err(e) = self.video.player_mut().set_rate(rate) {
+                self.notify_error(&e, None);
+            }
+        }
     }
 
-    /// Opens a file dialog for the user to select a video file
-    fn select_file(&mut self) -> Result<(), PlayerError> {
+    /// Opens a file dialog for the user to select a video file or a playlist
+    fn select_file(&mut self) {
         if let Some(path) = FileDialog::new()
-            .add_filter("Video", &["mp4", "webm", "mkv", "avi", "mov"])
+            .add_filter("Video", &VIDEO_EXTENSIONS)
+            .add_filter("Playlist", &PlaylistFormat::EXTENSIONS)
             .pick_file()
         {
-            self.load_file(path)?;
+            let locations = self.locations(vec![path]);
+            if let Some(location) = locations.into_iter().next() {
+                self.open(location);
+            }
         }
-        Ok(())
     }
 
-    /// Loads and starts playing a video file from the given path
-    fn load_file(&mut self, path: PathBuf) -> Result<(), PlayerError> {
-        self.stop()?;
-        let uri = format!("file://{}", path.to_str().unwrap_or(""));
-        self.pipeline.set_property("uri", &uri);
-        self.duration = None;
-        self.position = Some(gst::ClockTime::ZERO);
-        self.play()?;
-        Ok(())
-    }
+    /// Draws the "Open URL" dialog while it is open and opens the URL once confirmed
+    fn show_url_dialog(&mut self, ctx: &egui::Context) {
+        let Some(url) = &mut self.url_input else {
+            return;
+        };
+        let mut open = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let mut rtsp = self.video.player().rtsp_settings();
+        egui::Window::new("Open URL")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(url)
+                        .hint_text("https://example.com/stream.m3u8")
+                        .desired_width(360.0),
+                );
+                if ui.memory(|memory| memory.focused().is_none()) {
+                    response.request_focus();
+                }
+                open = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                egui::CollapsingHeader::new("RTSP").show(ui, |ui| rtsp_settings_ui(ui, &mut rtsp));
+                ui.horizontal(|ui| {
+                    open |= ui.button("Open").clicked();
+                    cancel |= ui.button("Cancel").clicked();
+                });
+            });
+        if rtsp != self.video.player().rtsp_settings() {
+            self.video.player_mut().set_rtsp_settings(rtsp);
+        }
 
-    /// Sets the playback volume (0.0 to 1.0)
-    fn set_volume(&mut self, volume: f64) {
-        self.volume = volume.clamp(0.0, 1.0);
-        self.pipeline.set_property("volume", self.volume);
+        if open {
+            let url = self.url_input.take().unwrap_or_default();
+            match MediaLocation::from_uri(&url) {
+                Ok(location) => self.open(location),
+                Err(e) => self.notify_error(&e.into(), None),
+            }
+        } else if cancel {
+            self.url_input = None;
+        }
     }
 
-    /// Gets the current state of the pipeline
-    fn get_state(&self) -> gst::State {
-        self.pipeline.current_state()
+    /// Opens a file dialog for the user to add video files to the playlist
+    fn select_files_to_enqueue(&mut self) {
+        if let Some(paths) = FileDialog::new()
+            .add_filter("Video", &VIDEO_EXTENSIONS)
+            .pick_files()
+        {
+            let locations = self.locations(paths);
+            self.enqueue(locations);
+        }
     }
 
-    /// Sets the pipeline to NULL state (completely stopped)
-    fn set_null(&mut self) -> Result<(), PlayerError> {
-        let ret = self
-            .pipeline
-            .set_state(gst::State::Null)
-            .map_err(|e| PlayerError::GstreamerError(format!("Failed to set null: {}", e)))?;
-        println!("Pipeline set to NULL state: {:?}", ret);
-        Ok(())
+    /// Opens a file dialog for the user to select a playlist file
+    fn select_playlist(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("Playlist", &PlaylistFormat::EXTENSIONS)
+            .pick_file()
+        {
+            self.open_playlist(path);
+        }
     }
 
-    /// Starts or resumes playback
-    fn play(&mut self) -> Result<(), PlayerError> {
-        let ret = self
-            .pipeline
-            .set_state(gst::State::Playing)
-            .map_err(|e| PlayerError::GstreamerError(format!("Failed to play: {}", e)))?;
-        println!("Pipeline set to PLAYING state: {:?}", ret);
-        Ok(())
+    /// Opens a file dialog for the user to save the playlist, in the format
+    /// matching the chosen extension
+    fn save_playlist(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("M3U", &["m3u8", "m3u"])
+            .add_filter("PLS", &["pls"])
+            .add_filter("XSPF", &["xspf"])
+            .set_file_name("playlist.m3u8")
+            .save_file()
+        {
+            if let Err(e) = PlaylistFormat::write(&path, self.playlist.items()) {
+                self.notify_error(&e, None);
+            }
+        }
     }
 
-    /// Pauses playback
-    fn pause(&mut self) -> Result<(), PlayerError> {
-        let ret = self
-            .pipeline
-            .set_state(gst::State::Paused)
-            .map_err(|e| PlayerError::GstreamerError(format!("Failed to pause: {}", e)))?;
-        println!("Pipeline set to PAUSED state: {:?}", ret);
-        Ok(())
+    /// Replaces the playlist with the items of a playlist file and plays the first one
+    fn open_playlist(&mut self, path: PathBuf) {
+        match PlaylistFormat::read(&path) {
+            Ok(items) => {
+                self.playlist.clear();
+                for item in items {
+                    self.playlist.push(item);
+                }
+                self.play_item(0);
+                self.show_playlist = true;
+            }
+            Err(e) => self.notify_error(&e, None),
+        }
     }
 
-    /// Stops playback and resets position
-    fn stop(&mut self) -> Result<(), PlayerError> {
-        let ret = self
-            .pipeline
-            .set_state(gst::State::Ready)
-            .map_err(|e| PlayerError::GstreamerError(format!("Failed to stop: {}", e)))?;
-        println!("Pipeline set to READY state: {:?}", ret);
-        self.position = Some(gst::ClockTime::ZERO);
-        Ok(())
+    /// Replaces the playlist with a single video file or stream and plays it.
+    /// Playlist files are opened as playlists.
+    fn open(&mut self, location: MediaLocation) {
+        if let Some(path) = location
+            .path()
+            .filter(|path| PlaylistFormat::from_path(path).is_some())
+        {
+            self.open_playlist(path.to_path_buf());
+            return;
+        }
+        self.playlist.clear();
+        self.playlist.enqueue(location);
+        self.play_item(0);
     }
 
-    /// Toggles between playing and paused states
-    fn toggle_playback(&mut self) -> Result<(), PlayerError> {
-        match self.get_state() {
-            gst::State::Playing => self.pause(),
-            gst::State::Paused | gst::State::Ready => self.play(),
-            _ => Ok(()),
+    /// Adds video files or streams to the playlist, starting playback if nothing is
+    /// playing yet
+    fn enqueue(&mut self, locations: Vec<MediaLocation>) {
+        if locations.is_empty() {
+            return;
         }
+        let first = self.playlist.len();
+        for location in locations {
+            self.playlist.enqueue(location);
+        }
+        if self.playlist.current_index().is_none() {
+            self.play_item(first);
+        }
+        self.show_playlist = true;
     }
 
-    /// Updates the current playback position and duration
-    fn update_position(&mut self) {
-        if let Some(position) = self.pipeline.query_position::<gst::ClockTime>() {
-            self.position = Some(position);
+    /// Opens what was dropped onto the window: a single file replaces the current media,
+    /// several files are enqueued and folders add the media files inside them
+    fn open_dropped(&mut self, paths: Vec<PathBuf>) {
+        if let [path] = paths.as_slice() {
+            if !path.is_dir() {
+                let locations = self.locations(paths);
+                if let Some(location) = locations.into_iter().next() {
+                    self.open(location);
+                }
+                return;
+            }
         }
 
-        if self.duration.is_none() {
-            if let Some(duration) = self.pipeline.query_duration::<gst::ClockTime>() {
-                self.duration = Some(duration);
+        let mut files = Vec::new();
+        for path in paths {
+            if !path.is_dir() {
+                files.push(path);
+                continue;
+            }
+            match media_files_in(&path) {
+                Ok(found) => files.extend(found),
+                Err(e) => {
+                    self.notifications
+                        .warning(format!("Could not read {}: {}", path.display(), e))
+                }
             }
         }
+        let locations = self.locations(files);
+        self.enqueue(locations);
     }
 
-    /// Seeks to a specific position (0.0 to 1.0) in the video
-    fn seek(&mut self, position: f64) -> Result<(), PlayerError> {
-        if let Some(duration) = self.duration {
-            let position = (position * duration.nseconds() as f64) as i64;
-            self.pipeline
-                .seek_simple(
-                    gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
-                    gst::ClockTime::from_nseconds(position as u64),
-                )
-                .map_err(|e| PlayerError::GstreamerError(format!("Failed to seek: {}", e)))?;
+    /// Plays the playlist item at the given index
+    fn play_item(&mut self, index: usize) {
+        if let Some(location) = self.playlist.select(index).cloned() {
+            self.load(location);
         }
-        Ok(())
     }
 
-    /// Updates the Egui texture with the current video frame
-    fn update_texture(&mut self, ctx: &egui::Context) {
-        if let Some(frame) = self.video_frame.lock().unwrap().as_ref() {
-            self.texture = Some(ctx.load_texture(
-                "video-frame",
-                egui::ColorImage::from_rgba_unmultiplied(
-                    [frame.width as usize, frame.height as usize],
-                    &frame.data,
-                ),
-                egui::TextureOptions::LINEAR,
-            ));
+    /// Skips to the next playlist item
+    fn next(&mut self) {
+        if let Some(location) = self.playlist.next_item().cloned() {
+            self.load(location);
         }
     }
 
+    /// Goes back to the previous playlist item
+    fn previous(&mut self) {
+        if let Some(location) = self.playlist.previous_item().cloned() {
+            self.load(location);
+        }
+    }
+
+    /// Opens a video file or stream, reporting failures as a notification
+    fn load(&mut self, location: MediaLocation) {
+        self.current_media = Some(location.clone());
+        if let Err(e) = self.video.open_location(&location) {
+            self.notify_error(&e, Some(RetryAction::Open(location)));
+        }
+    }
+
+    /// Toggles playback, reporting failures as a notification
+    fn toggle_playback(&mut self) {
+        if let Err(e) = self.video.player_mut().toggle_playback() {
+            self.notify_error(&e, Some(RetryAction::TogglePlayback));
+        }
+    }
+
+    /// Runs an action the user retried from a notification
+    fn retry(&mut self, action: RetryAction) {
+        match action {
+            RetryAction::Open(location) => self.load(location),
+            RetryAction::TogglePlayback => self.toggle_playback(),
+        }
+    }
+
+    /// Shows an error notification, with a retry button if there is something to retry
+    fn notify_error(&mut self, error: &PlayerError, retry: Option<RetryAction>) {
+        let mut notification = Notification::from_error(error);
+        notification.retry = retry;
+        self.notifications.push(notification);
+    }
+
     /// Toggles fullscreen mode
     fn toggle_fullscreen(&mut self, ctx: &egui::Context) {
         let is_fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
@@ -330,185 +366,496 @@ impl MediaPlayer {
     }
 }
 
-/// Implementation of the Egui App trait for our MediaPlayer
-impl eframe::App for MediaPlayer {
+/// Implementation of the Egui App trait for our PlayerApp
+impl eframe::App for PlayerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Process any pending GStreamer events
-        while self.main_context.iteration(false) {}
-
-        // Handle keyboard shortcuts
-        if ctx.input(|i| i.key_pressed(egui::Key::Space)) {
-            let _ = self.toggle_playback();
+        // React to what happened in the pipeline since the last frame
+        let events: Vec<PlayerEvent> = self.video.player_mut().poll_events().collect();
+        for event in events {
+            match event {
+                PlayerEvent::Error(error) => {
+                    // Pipeline errors are retried by opening the same file again,
+                    // unless the player already retries a failed network stream
+                    let retry = match self.video.player().pending_retry() {
+                        Some(_) => None,
+                        None => self.current_media.clone().map(RetryAction::Open),
+                    };
+                    self.notify_error(&error, retry);
+                }
+                PlayerEvent::Reconnecting {
+                    attempt,
+                    max_attempts,
+                } => {
+                    self.notifications.push(Notification::new(
+                        NotificationLevel::Info,
+                        format!("Reconnecting, attempt {} of {}", attempt, max_attempts),
+                    ));
+                }
+                PlayerEvent::Warning {
+                    source,
+                    message,
+                    debug,
+                } => {
+                    let mut details = message.clone();
+                    if let Some(source) = source {
+                        details.push_str(&format!("\nSource: {}", source));
+                    }
+                    if let Some(debug) = debug {
+                        details.push_str(&format!("\nDebug: {}", debug));
+                    }
+                    self.notifications.push(
+                        Notification::new(NotificationLevel::Warning, message)
+                            .with_details(details),
+                    );
+                }
+                PlayerEvent::MissingPlugin(missing) => {
+                    let mut message = format!("Missing plugin: {}", missing.description);
+                    if let Some(plugin_set) = missing.plugin_set() {
+                        message.push_str(&format!("\n{}", plugin_set.install_hint()));
+                    }
+                    self.notifications.push(
+                        Notification::new(NotificationLevel::Warning, message)
+                            .with_details(missing.installer_detail.clone()),
+                    );
+                }
+                PlayerEvent::StateChanged {
+                    old: gst::State::Ready,
+                    current: gst::State::Paused,
+                    ..
+                } => self.apply_start_options(),
+                PlayerEvent::EndOfStream if self.exit_on_eos => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                PlayerEvent::MediaChanged(uri) => {
+                    // The player moved on to the queued item by itself
+                    if self.playlist.select_uri(&uri).is_none() {
+                        self.playlist.advance();
+                    }
+                    self.current_media = self.playlist.current().map(|item| item.location.clone());
+                }
+                PlayerEvent::Tags(tags) => {
+                    if let Some(title) = tags.get::<gst::tags::Title>() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                            "{} - Video Player",
+                            title.get()
+                        )));
+                    }
+                }
+                _ => (),
+            }
         }
 
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-            self.fullscreen_off(ctx);
+        // Open or enqueue files dropped onto the window
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        if !dropped.is_empty() {
+            self.open_dropped(dropped);
         }
 
-        if ctx.input(|i| i.key_pressed(egui::Key::F11)) {
-            println!("F11 pressed - toggling fullscreen");
-            self.toggle_fullscreen(ctx);
+        // Keep the next item queued for a gapless transition
+        self.video
+            .player_mut()
+            .queue_next(self.playlist.peek_next());
+        if let (Some(location), Some(duration)) =
+            (&self.current_media, self.video.player().duration())
+        {
+            self.playlist.set_duration(location, duration);
         }
 
-        // Determine the play/pause button text based on current state
-        let play_button_text = match self.get_state() {
-            gst::State::Playing => "⏸",
-            _ => "⏵",
-        };
+        // Handle keyboard shortcuts, unless the user is typing into a text field
+        if !ctx.wants_keyboard_input() {
+            if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::U)) {
+                self.url_input = Some(String::new());
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::Space)) {
+                self.toggle_playback();
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::OpenBracket)) {
+                if let Err(e) = self.video.player_mut().slower() {
+                    self.notify_error(&e, None);
+                }
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::CloseBracket)) {
+                if let Err(e) = self.video.player_mut().faster() {
+                    self.notify_error(&e, None);
+                }
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::Comma)) {
+                if let Err(e) = self.video.player_mut().step_backward() {
+                    self.notify_error(&e, None);
+                }
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::Period)) {
+                if let Err(e) = self.video.player_mut().step_forward() {
+                    self.notify_error(&e, None);
+                }
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::N)) {
+                self.next();
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::L)) && self.video.player().is_behind_live() {
+                if let Err(e) = self.video.player_mut().go_to_live() {
+                    self.notify_error(&e, None);
+                }
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::P)) {
+                self.previous();
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.fullscreen_off(ctx);
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::F11)) {
+                self.toggle_fullscreen(ctx);
+            }
+        }
 
         // Auto-hide controls in fullscreen mode after 3 seconds of inactivity
         let inactive = ctx.input(|i| i.pointer.time_since_last_movement() > 3.0);
         let controls_shown = !self.is_fullscreen(ctx) || !inactive;
         if inactive {
             ctx.output_mut(|o| o.cursor_icon = egui::CursorIcon::None);
+        } else if self.is_fullscreen(ctx) {
+            // Nothing else repaints while paused, so wake up to hide the controls
+            let idle = ctx.input(|i| i.pointer.time_since_last_movement());
+            ctx.request_repaint_after(Duration::from_secs_f32((3.0 - idle).max(0.0) + 0.1));
         }
-
-        // Keep our state updated
-        self.update_position();
-        self.update_texture(ctx);
+        self.video.set_controls(controls_shown);
 
         // Create the top menu bar
         egui::TopBottomPanel::top("top_panel").show_animated(ctx, controls_shown, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("Open file").clicked() {
-                        if let Err(e) = self.select_file() {
-                            eprintln!("Error selecting file: {}", e);
-                        }
+                        self.select_file();
+                        ui.close_menu();
+                    }
+                    if ui.button("Open URL").clicked() {
+                        self.url_input = Some(String::new());
+                        ui.close_menu();
+                    }
+                    if ui.button("Add to playlist").clicked() {
+                        self.select_files_to_enqueue();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Open playlist").clicked() {
+                        self.select_playlist();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.playlist.is_empty(),
+                            egui::Button::new("Save playlist"),
+                        )
+                        .clicked()
+                    {
+                        self.save_playlist();
                         ui.close_menu();
                     }
+                    ui.separator();
                     if ui.button("Quit").clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
-                ui.menu_button("View", |ui| {
-                    if ui.button("Toggle fullscreen").clicked() {
-                        self.toggle_fullscreen(ctx);
+                ui.menu_button("Playback", |ui| {
+                    if ui.button("Next").clicked() {
+                        self.next();
                         ui.close_menu();
                     }
-                });
-            });
-        });
-
-        // Create the bottom control panel with playback controls
-        egui::TopBottomPanel::bottom("video_controls").show_animated(ctx, controls_shown, |ui| {
-            ui.add_space(3.0);
-            ui.horizontal(|ui| {
-                // Play/Pause and Stop buttons
-                if ui.button(play_button_text).clicked() {
-                    let _ = self.toggle_playback();
-                }
-                if ui.button("⏹").clicked() {
-                    let _ = self.stop();
-                }
-
-                // Position slider
-                ui.style_mut().spacing.slider_width = ui.available_width() - 240.0;
-                if let (Some(position), Some(duration)) = (self.position, self.duration) {
-                    let mut pos = position.seconds() as f64 / duration.seconds() as f64;
+                    if ui.button("Previous").clicked() {
+                        self.previous();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.menu_button("At end of file", |ui| {
+                        let current = self.video.options().eos_action;
+                        for (label, action) in [
+                            ("Stop and rewind", EosAction::Stop),
+                            ("Hold last frame", EosAction::HoldLastFrame),
+                            ("Loop", EosAction::Loop),
+                            ("Play next", EosAction::Next),
+                        ] {
+                            if ui.radio(current == action, label).clicked() {
+                                if let Err(e) = self.video.set_eos_action(action) {
+                                    self.notify_error(&e, None);
+                                }
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    let mut time_shift = self.video.player().time_shift().is_some();
                     if ui
-                        .add(egui::Slider::new(&mut pos, 0.0..=1.0).show_value(false))
+                        .checkbox(&mut time_shift, "Time-shift buffer")
+                        .on_hover_text("Record live streams so they can be paused and rewound")
                         .changed()
                     {
-                        let _ = self.seek(pos);
-                    }
-                } else {
-                    let mut pos = 0.0;
-                    ui.add(
-                        egui::Slider::new(&mut pos, 0.0..=1.0)
-                            .step_by(0.0)
-                            .show_value(false),
-                    );
-                }
-
-                // Time display and volume controls
-                ui.horizontal(|ui| {
-                    ui.set_width(240.0);
-                    if let (Some(position), Some(duration)) = (self.position, self.duration) {
-                        ui.label(format!(
-                            "{:02}:{:02} / {:02}:{:02}",
-                            position.seconds() / 60,
-                            position.seconds() % 60,
-                            duration.seconds() / 60,
-                            duration.seconds() % 60
-                        ));
-                    } else {
-                        ui.label("00:00 / 00:00");
+                        let time_shift = time_shift.then(TimeShift::default);
+                        if let Err(e) = self.video.player_mut().set_time_shift(time_shift) {
+                            self.notify_error(&e, None);
+                        }
                     }
-                    ui.separator();
-                    ui.label("🔊");
-                    let mut volume = self.volume;
-                    ui.style_mut().spacing.slider_width = ui.available_width() - 36.0;
                     if ui
-                        .add(egui::Slider::new(&mut volume, 0.0..=1.0).show_value(false))
-                        .changed()
+                        .add_enabled(
+                            self.video.player().is_behind_live(),
+                            egui::Button::new("Go to live"),
+                        )
+                        .clicked()
                     {
-                        self.set_volume(volume);
+                        if let Err(e) = self.video.player_mut().go_to_live() {
+                            self.notify_error(&e, None);
+                        }
+                        ui.close_menu();
                     }
-                    if ui.button("🗖").clicked() {
+                });
+                ui.menu_button("View", |ui| {
+                    if ui.button("Toggle fullscreen").clicked() {
                         self.toggle_fullscreen(ctx);
+                        ui.close_menu();
                     }
+                    ui.checkbox(&mut self.show_playlist, "Playlist");
+                    ui.menu_button("Aspect ratio", |ui| {
+                        let current = (self.video.options().aspect, self.video.options().fit);
+                        for (label, aspect, fit) in [
+                            ("Auto", AspectRatio::Auto, FitMode::Contain),
+                            ("4:3", AspectRatio::RATIO_4_3, FitMode::Contain),
+                            ("16:9", AspectRatio::RATIO_16_9, FitMode::Contain),
+                            ("2.35:1", AspectRatio::RATIO_2_35_1, FitMode::Contain),
+                            ("Stretch to fill", AspectRatio::Auto, FitMode::Fill),
+                            ("Crop to fill", AspectRatio::Auto, FitMode::Cover),
+                        ] {
+                            if ui.radio(current == (aspect, fit), label).clicked() {
+                                self.video.set_aspect(aspect);
+                                self.video.set_fit(fit);
+                                ui.close_menu();
+                            }
+                        }
+                    });
                 });
             });
-            ui.add_space(3.0);
         });
 
+        // Playlist next to the video
+        egui::SidePanel::right("playlist")
+            .resizable(true)
+            .default_width(220.0)
+            .show_animated(ctx, self.show_playlist && controls_shown, |ui| {
+                if let Some(index) = self.playlist.ui(ui) {
+                    self.play_item(index);
+                }
+            });
+
         // Main video display area
         egui::CentralPanel::default()
             .frame(egui::Frame::none().fill(ctx.style().visuals.panel_fill))
             .show(ctx, |ui| {
-                if let Some(texture) = &self.texture {
-                    // Calculate video dimensions while maintaining aspect ratio
-                    let original_size = texture.size_vec2();
-                    let aspect_ratio = original_size.x / original_size.y;
-                    let mut scaled_height = ui.available_height();
-                    let mut scaled_width = scaled_height * aspect_ratio;
-                    if scaled_width > ui.available_width() {
-                        scaled_width = ui.available_width();
-                        scaled_height = scaled_width / aspect_ratio;
-                    }
+                let response = ui.add(&mut self.video);
 
-                    // Display the video frame
-                    egui::Frame::none()
-                        .fill(egui::Color32::BLACK)
-                        .show(ui, |ui| {
-                            ui.centered_and_justified(|ui| {
-                                ui.add(egui::Image::new((
-                                    texture.id(),
-                                    egui::vec2(scaled_width, scaled_height),
-                                )));
-                            });
-                        });
-                } else {
-                    // Show file selection button when no video is loaded
-                    ui.vertical_centered(|ui| {
-                        ui.add_space(ui.available_height() / 2.0);
-                        if ui.button("Select file").clicked() {
-                            if let Err(e) = self.select_file() {
-                                eprintln!("Error selecting file: {}", e);
-                            }
-                        }
-                    });
+                // Show file selection button when no video is loaded
+                if !self.video.has_frame() {
+                    let button_rect = egui::Rect::from_center_size(
+                        response.rect.center(),
+                        egui::vec2(100.0, 24.0),
+                    );
+                    if ui
+                        .put(button_rect, egui::Button::new("Select file"))
+                        .clicked()
+                    {
+                        self.select_file();
+                    }
                 }
             });
 
-        // Request continuous updates for smooth playback
-        ctx.request_repaint_after(Duration::from_millis(16)); // ~60 FPS
+        self.show_url_dialog(ctx);
+
+        // Notifications are drawn over the video
+        if let Some(action) = self.notifications.show(ctx) {
+            self.retry(action);
+        }
+
+        // Highlight the window while files are dragged over it
+        let hovered = ctx.input(|i| {
+            let files = &i.raw.hovered_files;
+            (!files.is_empty())
+                .then(|| files.len() == 1 && !files[0].path.as_deref().is_some_and(Path::is_dir))
+        });
+        if let Some(replaces) = hovered {
+            paint_drop_overlay(ctx, replaces);
+        }
     }
 
     /// Set state to NULL on exit to prevent gstreamer memory leaks
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        let _ = self.set_null();
+        let _ = self.video.player_mut().set_null();
     }
 }
 
+/// Draws the settings used to receive RTSP streams
+fn rtsp_settings_ui(ui: &mut egui::Ui, settings: &mut RtspSettings) {
+    egui::Grid::new("rtsp_settings")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Transport");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut settings.transport, RtspTransport::Auto, "Auto");
+                ui.radio_value(&mut settings.transport, RtspTransport::Tcp, "TCP");
+                ui.radio_value(&mut settings.transport, RtspTransport::Udp, "UDP");
+            });
+            ui.end_row();
+
+            ui.label("Latency");
+            let mut latency = settings.latency.mseconds();
+            if ui
+                .add(
+                    egui::DragValue::new(&mut latency)
+                        .range(0..=10_000)
+                        .suffix(" ms"),
+                )
+                .on_hover_text("Size of the jitter buffer")
+                .changed()
+            {
+                settings.latency = gst::ClockTime::from_mseconds(latency);
+            }
+            ui.end_row();
+
+            for (label, value, password) in [
+                ("User", &mut settings.user_id, false),
+                ("Password", &mut settings.user_pw, true),
+            ] {
+                ui.label(label);
+                let mut text = value.clone().unwrap_or_default();
+                if ui
+                    .add(egui::TextEdit::singleline(&mut text).password(password))
+                    .changed()
+                {
+                    *value = Some(text).filter(|text| !text.is_empty());
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// Dims the window and tells the user what dropping the hovered files will do
+fn paint_drop_overlay(ctx: &egui::Context, replaces: bool) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("drop_overlay"),
+    ));
+    let rect = ctx.screen_rect();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+    painter.rect_stroke(
+        rect.shrink(4.0),
+        8.0,
+        egui::Stroke::new(3.0, ctx.style().visuals.selection.bg_fill),
+    );
+    let text = if replaces {
+        "Drop to play"
+    } else {
+        "Drop to add to the playlist"
+    };
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        text,
+        egui::FontId::proportional(24.0),
+        egui::Color32::WHITE,
+    );
+}
+
+/// Lists the media files directly inside a folder in natural sort order
+fn media_files_in(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| {
+                        VIDEO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                    })
+        })
+        .collect();
+    files.sort_by(|a, b| {
+        natural_cmp(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    Ok(files)
+}
+
+/// Compares file names the way people expect, so "Episode 2" sorts before "Episode 10".
+/// Runs of digits compare by value, everything else case-insensitively.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        let ordering = match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_digits(&mut a);
+                let y = take_digits(&mut b);
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => {
+                a.next();
+                b.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Takes the run of ASCII digits at the front of the iterator
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    digits
+}
+
 /// Main entry point for the application
 fn main() -> Result<(), eframe::Error> {
+    let cli = match CliOptions::parse(std::env::args_os().skip(1)) {
+        Ok(cli) => cli,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
     // Configure the application window
     let options = eframe::NativeOptions {
         viewport: ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
             .with_min_inner_size([400.0, 300.0])
+            .with_fullscreen(cli.fullscreen)
             .with_title("Video Player"),
         ..Default::default()
     };
@@ -518,12 +865,12 @@ fn main() -> Result<(), eframe::Error> {
         "Video Player",
         options,
         Box::new(
-            |_cc| -> Result<
+            move |_cc| -> Result<
                 Box<dyn eframe::App>,
                 Box<dyn std::error::Error + Send + Sync + 'static>,
             > {
-                let player = MediaPlayer::new()?;
-                Ok(Box::new(player))
+                let app = PlayerApp::new(cli)?;
+                Ok(Box::new(app))
             },
         ),
     )
@@ -0,0 +1,101 @@
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+
+/// The upstream plugin set a missing GStreamer element is shipped in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginSet {
+    Base,
+    Good,
+    Bad,
+    Ugly,
+    Libav,
+}
+
+impl PluginSet {
+    /// Gets the package names of the plugin set as (Arch, Debian/Ubuntu, Fedora)
+    pub fn packages(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            PluginSet::Base => (
+                "gst-plugins-base",
+                "gstreamer1.0-plugins-base",
+                "gstreamer1-plugins-base",
+            ),
+            PluginSet::Good => (
+                "gst-plugins-good",
+                "gstreamer1.0-plugins-good",
+                "gstreamer1-plugins-good",
+            ),
+            PluginSet::Bad => (
+                "gst-plugins-bad",
+                "gstreamer1.0-plugins-bad",
+                "gstreamer1-plugins-bad-free",
+            ),
+            PluginSet::Ugly => (
+                "gst-plugins-ugly",
+                "gstreamer1.0-plugins-ugly",
+                "gstreamer1-plugins-ugly",
+            ),
+            PluginSet::Libav => ("gst-libav", "gstreamer1.0-libav", "gstreamer1-libav"),
+        }
+    }
+
+    /// Gets a one line hint telling the user which package to install
+    pub fn install_hint(self) -> String {
+        let (arch, debian, fedora) = self.packages();
+        format!(
+            "Install {} (Arch), {} (Debian/Ubuntu) or {} (Fedora)",
+            arch, debian, fedora
+        )
+    }
+}
+
+/// A plugin the pipeline needed but could not find, as reported by a
+/// missing-plugin element message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingPlugin {
+    pub description: String, // Human readable name, e.g. "H.264 (Main Profile) decoder"
+    pub installer_detail: String, // Opaque string for installers such as PackageKit
+}
+
+impl MissingPlugin {
+    /// Extracts the missing plugin from a bus message, if it is a missing-plugin message
+    pub fn from_message(msg: &gst::MessageRef) -> Option<Self> {
+        let missing = gst_pbutils::MissingPluginMessage::parse(msg).ok()?;
+        Some(MissingPlugin {
+            description: missing.description().to_string(),
+            installer_detail: missing.installer_detail().to_string(),
+        })
+    }
+
+    /// Gets the caps or URI protocol of what is missing, e.g. "video/x-h264" or "rtsp"
+    pub fn missing_type(&self) -> Option<&str> {
+        // gstreamer|1.0|<application>|<description>|<kind>-<caps or protocol>
+        let detail = self.installer_detail.split('|').nth(4)?;
+        let (_, what) = detail.split_once('-')?;
+        what.split(',').next().map(str::trim)
+    }
+
+    /// Guesses which plugin set provides what is missing
+    pub fn plugin_set(&self) -> Option<PluginSet> {
+        let plugin_set = match self.missing_type()? {
+            "video/x-h264" | "video/x-h265" | "video/mpeg" | "audio/mpeg" | "video/x-divx"
+            | "video/x-xvid" | "video/x-wmv" | "audio/x-wma" | "video/x-prores"
+            | "video/x-h263" | "audio/x-alac" => PluginSet::Libav,
+            "video/x-vp8" | "video/x-vp9" | "audio/x-flac" | "video/quicktime"
+            | "video/x-matroska" | "video/webm" | "video/x-msvideo" | "audio/x-wav" | "http"
+            | "https" | "rtsp" | "rtsps" | "udp" => PluginSet::Good,
+            "audio/x-opus" | "audio/x-vorbis" | "video/x-theora" | "application/ogg" => {
+                PluginSet::Base
+            }
+            "video/x-av1"
+            | "video/mpegts"
+            | "audio/x-dts"
+            | "application/x-hls"
+            | "application/dash+xml"
+            | "srt" => PluginSet::Bad,
+            "audio/x-ac3" | "video/x-dvd-subpicture" => PluginSet::Ugly,
+            _ => return None,
+        };
+        Some(plugin_set)
+    }
+}
@@ -0,0 +1,177 @@
+use eframe::egui;
+
+use crate::error::PlayerError;
+
+/// Seconds after which notifications that are not errors disappear on their own
+const AUTO_DISMISS_AFTER: f64 = 8.0;
+
+/// How serious a notification is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A single toast message, optionally carrying an action the user can retry
+#[derive(Debug, Clone)]
+pub struct Notification<A> {
+    pub level: NotificationLevel,
+    pub message: String,         // One line summary shown in the toast
+    pub details: Option<String>, // Longer text offered through "Copy details"
+    pub retry: Option<A>,        // Action handed back when "Retry" is clicked
+    created: Option<f64>,        // UI time the toast was first shown at
+}
+
+impl<A> Notification<A> {
+    /// Creates a notification without details or retry action
+    pub fn new(level: NotificationLevel, message: impl Into<String>) -> Self {
+        Notification {
+            level,
+            message: message.into(),
+            details: None,
+            retry: None,
+            created: None,
+        }
+    }
+
+    /// Creates an error notification from a player error, keeping its pipeline details
+    pub fn from_error(error: &PlayerError) -> Self {
+        let mut notification = Self::new(NotificationLevel::Error, error.to_string());
+        notification.details = error.details().map(|details| {
+            let mut text = details.message.clone();
+            if let Some(source) = &details.source {
+                text.push_str(&format!("\nSource: {}", source));
+            }
+            if let Some(debug) = &details.debug {
+                text.push_str(&format!("\nDebug: {}", debug));
+            }
+            text
+        });
+        notification
+    }
+
+    /// Adds longer details to the notification
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    /// Attaches an action the user can retry from the notification
+    pub fn with_retry(mut self, retry: A) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+/// A stack of toast notifications drawn over the rest of the UI.
+///
+/// `A` is the type of retry action the host application wants handed back.
+pub struct Notifications<A> {
+    notifications: Vec<Notification<A>>,
+}
+
+impl<A> Default for Notifications<A> {
+    fn default() -> Self {
+        Notifications {
+            notifications: Vec::new(),
+        }
+    }
+}
+
+impl<A> Notifications<A> {
+    /// Creates an empty notification stack
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a notification to the stack
+    pub fn push(&mut self, notification: Notification<A>) {
+        self.notifications.push(notification);
+    }
+
+    /// Adds an error notification for a player error
+    pub fn error(&mut self, error: &PlayerError) {
+        self.push(Notification::from_error(error));
+    }
+
+    /// Adds a warning notification
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.push(Notification::new(NotificationLevel::Warning, message));
+    }
+
+    /// Removes all notifications
+    pub fn clear(&mut self) {
+        self.notifications.clear();
+    }
+
+    /// Draws the notifications in the top right corner and returns the retry
+    /// action the user clicked, if any
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<A> {
+        let now = ctx.input(|i| i.time);
+        self.notifications.retain(|notification| {
+            notification.level == NotificationLevel::Error
+                || notification
+                    .created
+                    .is_none_or(|created| now - created < AUTO_DISMISS_AFTER)
+        });
+        if self.notifications.is_empty() {
+            return None;
+        }
+
+        let mut dismissed = None;
+        let mut retried = None;
+        egui::Area::new(egui::Id::new("notifications"))
+            .anchor(egui::Align2::RIGHT_TOP, [-12.0, 36.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(360.0);
+                for (index, notification) in self.notifications.iter_mut().enumerate() {
+                    let created = *notification.created.get_or_insert(now);
+                    let (icon, color) = match notification.level {
+                        NotificationLevel::Info => ("ℹ", ui.visuals().text_color()),
+                        NotificationLevel::Warning => ("⚠", ui.visuals().warn_fg_color),
+                        NotificationLevel::Error => ("🗙", ui.visuals().error_fg_color),
+                    };
+
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.colored_label(color, icon);
+                            ui.label(&notification.message);
+                        });
+                        ui.horizontal(|ui| {
+                            if notification.retry.is_some() && ui.button("Retry").clicked() {
+                                retried = Some(index);
+                            }
+                            if let Some(details) = &notification.details {
+                                if ui.button("Copy details").clicked() {
+                                    ui.ctx().copy_text(details.clone());
+                                }
+                            }
+                            if ui.button("Dismiss").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                    ui.add_space(4.0);
+
+                    // Wake up again to expire the toast
+                    if notification.level != NotificationLevel::Error {
+                        let remaining = AUTO_DISMISS_AFTER - (now - created);
+                        ui.ctx()
+                            .request_repaint_after(std::time::Duration::from_secs_f64(
+                                remaining.max(0.0),
+                            ));
+                    }
+                }
+            });
+
+        if let Some(index) = retried {
+            return self.notifications.remove(index).retry;
+        }
+        if let Some(index) = dismissed {
+            self.notifications.remove(index);
+        }
+        None
+    }
+}
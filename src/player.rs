@@ -0,0 +1,1896 @@
+use eframe::egui;
+use glib::{self, MainContext};
+use gstreamer as gst;
+use gstreamer::bus::BusWatchGuard;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_pbutils as gst_pbutils;
+use gstreamer_video as gst_video;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::location::{is_network_uri, MediaLocation};
+use crate::missing_plugin::MissingPlugin;
+use crate::retry::RetryPolicy;
+use crate::rtsp::RtspSettings;
+use crate::timeshift::{Recorder, Ring, TimeShift};
+
+/// Maximum number of events kept for the host before the oldest ones are dropped
+const MAX_PENDING_EVENTS: usize = 256;
+
+/// Playback rates offered by the UI, from slowest to fastest
+pub const PLAYBACK_RATES: [f64; 9] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 3.0, 4.0];
+
+/// Slowest supported playback rate, in either direction
+pub const MIN_RATE: f64 = 0.25;
+
+/// Fastest supported playback rate, in either direction
+pub const MAX_RATE: f64 = 4.0;
+
+/// How far behind the live edge playback may be and still count as live
+const LIVE_EDGE_TOLERANCE: gst::ClockTime = gst::ClockTime::from_seconds(3);
+
+/// How long a reopened stream without video has to play before it counts as recovered
+const RECOVERY_PERIOD: Duration = Duration::from_secs(10);
+
+/// Demuxers that can play every frame backwards, others only manage keyframes
+const FULL_REVERSE_DEMUXERS: [&str; 2] = ["qtdemux", "matroskademux"];
+
+/// How precisely a seek lands on the requested position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeekMode {
+    /// Jump to the nearest keyframe, fast but may land seconds away in long-GOP files
+    Keyframe,
+    /// Decode up to the exact position, slower but frame accurate
+    #[default]
+    Accurate,
+    /// Jump to the keyframe at or before the requested position
+    SnapBefore,
+    /// Jump to the keyframe at or after the requested position
+    SnapAfter,
+}
+
+impl SeekMode {
+    /// Gets the seek flags for the mode, without FLUSH
+    fn flags(self) -> gst::SeekFlags {
+        match self {
+            SeekMode::Keyframe => gst::SeekFlags::KEY_UNIT,
+            SeekMode::Accurate => gst::SeekFlags::ACCURATE,
+            SeekMode::SnapBefore => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_BEFORE,
+            SeekMode::SnapAfter => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_AFTER,
+        }
+    }
+}
+
+/// How well the current media can be played backwards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReverseSupport {
+    /// Not known yet, no media is loaded or it has not prerolled
+    #[default]
+    Unknown,
+    /// Every frame can be played backwards
+    Full,
+    /// Only keyframes can be played backwards, like a slideshow
+    KeyframesOnly,
+    /// The media cannot be played backwards at all
+    Unsupported,
+}
+
+/// What the player does when the end of the media is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EosAction {
+    /// Pause and rewind to the start
+    #[default]
+    Stop,
+    /// Pause on the last frame
+    HoldLastFrame,
+    /// Start over seamlessly, using segment seeks so there is no flush or gap
+    Loop,
+    /// Continue gaplessly with the media given to [`MediaPlayer::queue_next`],
+    /// or pause on the last frame when nothing is queued
+    Next,
+}
+
+/// The part of the media playback loops over with segment seeks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoopRange {
+    None,
+    Whole,
+    Between(gst::ClockTime, gst::ClockTime), // A-B loop
+}
+
+impl LoopRange {
+    /// Gets the loop range from the A-B loop, or the whole media when looping at EOS
+    fn new(eos_action: EosAction, ab_loop: Option<(gst::ClockTime, gst::ClockTime)>) -> Self {
+        match (ab_loop, eos_action) {
+            (Some((a, b)), _) => LoopRange::Between(a, b),
+            (None, EosAction::Loop) => LoopRange::Whole,
+            (None, _) => LoopRange::None,
+        }
+    }
+}
+
+/// Seeks so playback continues from `position` in the direction of `rate`, `None`
+/// meaning the beginning in that direction. Reverse playback runs from the position
+/// back to the start, and is retried with keyframes only when the demuxer refuses it.
+/// When looping, playback stays inside the loop range and the pipeline posts SegmentDone
+/// instead of EOS at its end. Returns whether the keyframe fallback was used.
+fn seek_in_direction(
+    pipeline: &gst::Element,
+    rate: f64,
+    flags: gst::SeekFlags,
+    position: Option<gst::ClockTime>,
+    range: LoopRange,
+    keyframes_only: bool,
+) -> Result<bool, glib::BoolError> {
+    let flags = match range {
+        LoopRange::None => flags,
+        LoopRange::Whole | LoopRange::Between(..) => flags | gst::SeekFlags::SEGMENT,
+    };
+    let (low, high) = match range {
+        LoopRange::Between(a, b) => (a, Some(b)),
+        LoopRange::None | LoopRange::Whole => (gst::ClockTime::ZERO, None),
+    };
+    let (start, stop_type, stop) = if rate >= 0.0 {
+        let (stop_type, stop) = match high {
+            Some(b) => (gst::SeekType::Set, Some(b)),
+            None => (gst::SeekType::None, gst::ClockTime::NONE),
+        };
+        (position.unwrap_or(low), stop_type, stop)
+    } else {
+        let (stop_type, stop) = match position.or(high) {
+            Some(stop) => (gst::SeekType::Set, Some(stop)),
+            None => (gst::SeekType::End, Some(gst::ClockTime::ZERO)),
+        };
+        (low, stop_type, stop)
+    };
+
+    let seek = |flags| {
+        pipeline.seek(
+            rate,
+            flags,
+            gst::SeekType::Set,
+            Some(start),
+            stop_type,
+            stop,
+        )
+    };
+    if rate >= 0.0 {
+        seek(flags)?;
+        return Ok(false);
+    }
+    if !keyframes_only && seek(flags).is_ok() {
+        return Ok(false);
+    }
+    seek(flags | gst::SeekFlags::KEY_UNIT | gst::SeekFlags::TRICKMODE_KEY_UNITS)?;
+    Ok(true)
+}
+
+/// Pauses the pipeline from the bus watch, where the end of the media is handled
+fn pause_pipeline(pipeline: &gst::Element) -> Result<(), PlayerError> {
+    pipeline
+        .set_state(gst::State::Paused)
+        .map(|_| ())
+        .map_err(|e| PlayerError::state_change(format!("Failed to pause: {}", e)))
+}
+
+/// Seeks from the bus watch to rewind or loop once the end of the media or of a
+/// loop segment is reached. Live streams that ended have nothing to go back to.
+fn seek_after_eos(
+    pipeline: &gst::Element,
+    rate: f64,
+    flags: gst::SeekFlags,
+    range: LoopRange,
+) -> Result<(), PlayerError> {
+    let mut query = gst::query::Seeking::new(gst::Format::Time);
+    if pipeline.query(&mut query) && !query.result().0 {
+        return Ok(());
+    }
+    seek_in_direction(pipeline, rate, flags, None, range, false)
+        .map(|_| ())
+        .map_err(|e| PlayerError::GstreamerError(format!("Failed to seek back: {}", e)))
+}
+
+/// Represents a single frame of video data
+pub struct VideoFrame {
+    pub width: i32,
+    pub height: i32,
+    pub pixel_aspect_ratio: (i32, i32), // Width and height of a pixel, (1, 1) for square pixels
+    pub pts: Option<gst::ClockTime>,    // Presentation timestamp in stream time
+    pub duration: Option<gst::ClockTime>, // How long the frame is shown at normal rate
+    pub sequence: u64,                  // Increases with every frame, never repeats for a player
+    pub pixels: Vec<egui::Color32>,     // Tightly packed rows of width pixels, empty once taken
+}
+
+impl VideoFrame {
+    /// Gets the number of the frame counted from the start of the stream
+    pub fn number(&self) -> Option<u64> {
+        let pts = self.pts?.nseconds();
+        let duration = self.duration?.nseconds();
+        (duration > 0).then(|| (pts + duration / 2) / duration)
+    }
+
+    /// Gets the presentation timestamp of the frame before this one
+    pub fn previous_pts(&self) -> Option<gst::ClockTime> {
+        Some(self.pts?.saturating_sub(self.duration?))
+    }
+
+    /// Gets the aspect ratio the frame is meant to be displayed at, taking
+    /// non-square pixels into account
+    pub fn display_aspect_ratio(&self) -> f32 {
+        let (par_n, par_d) = self.pixel_aspect_ratio;
+        (self.width as f32 * par_n as f32) / (self.height as f32 * par_d as f32)
+    }
+}
+
+/// Converts a mapped RGBA frame into `pixels`, dropping the padding GStreamer may add
+/// at the end of each row. The vector is cleared first, so its allocation is reused.
+fn frame_pixels(
+    frame: &gst_video::VideoFrameRef<&gst::BufferRef>,
+    pixels: &mut Vec<egui::Color32>,
+) -> Result<(), glib::BoolError> {
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let row_size = width * 4;
+    let stride = frame.plane_stride()[0] as usize;
+    // plane_data already starts at the plane offset
+    let plane = frame.plane_data(0)?;
+    if stride < row_size || plane.len() < stride * height.saturating_sub(1) + row_size {
+        return Err(glib::bool_error!("Video frame is smaller than its caps"));
+    }
+
+    // Video frames are opaque, so the RGBA data is already premultiplied
+    pixels.clear();
+    pixels.reserve(width * height);
+    for row in plane.chunks(stride).take(height) {
+        pixels.extend(
+            row[..row_size]
+                .chunks_exact(4)
+                .map(|p| egui::Color32::from_rgba_premultiplied(p[0], p[1], p[2], p[3])),
+        );
+    }
+    Ok(())
+}
+
+/// Copies a sample from the appsink into the shared frame storage and wakes the UI up.
+/// The pixels are converted into `spare`, which gets the replaced frame's pixels back.
+fn store_sample(
+    sample: &gst::Sample,
+    video_frame: &Mutex<Option<VideoFrame>>,
+    spare: &mut Vec<egui::Color32>,
+    frame_sequence: &AtomicU64,
+    repaint_context: &OnceLock<egui::Context>,
+) -> Result<gst::FlowSuccess, gst::FlowError> {
+    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+    let caps = sample.caps().ok_or(gst::FlowError::Error)?;
+
+    // Get video dimensions from the caps
+    let video_info = gst_video::VideoInfo::from_caps(caps).map_err(|_| gst::FlowError::Error)?;
+    let width = video_info.width() as i32;
+    let height = video_info.height() as i32;
+
+    // Map the buffer as a video frame so plane offsets and strides are known
+    let mapped_frame = gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, &video_info)
+        .map_err(|_| gst::FlowError::Error)?;
+
+    // Timestamps in stream time, the same time base as the position and seeks
+    let pts = buffer.pts().and_then(|pts| {
+        sample
+            .segment()
+            .and_then(|segment| segment.downcast_ref::<gst::ClockTime>())
+            .and_then(|segment| segment.to_stream_time(pts))
+    });
+    let fps = video_info.fps();
+    let frame_duration = buffer.duration().or_else(|| {
+        (fps.numer() > 0).then(|| {
+            gst::ClockTime::from_nseconds(
+                gst::ClockTime::SECOND.nseconds() * fps.denom() as u64 / fps.numer() as u64,
+            )
+        })
+    });
+
+    let par = video_info.par();
+    let pixel_aspect_ratio = if par.numer() > 0 && par.denom() > 0 {
+        (par.numer(), par.denom())
+    } else {
+        (1, 1)
+    };
+    // Convert before locking, so the UI is never blocked by the copy
+    frame_pixels(&mapped_frame, spare).map_err(|_| gst::FlowError::Error)?;
+
+    let frame = VideoFrame {
+        width,
+        height,
+        pixel_aspect_ratio,
+        pts,
+        duration: frame_duration,
+        sequence: frame_sequence.fetch_add(1, Ordering::Relaxed) + 1,
+        pixels: std::mem::take(spare),
+    };
+    // Keep the previous frame's pixels for the next sample. They are only gone
+    // when the UI took them with take_frame.
+    let previous = video_frame.lock().unwrap().replace(frame);
+    if let Some(previous) = previous {
+        *spare = previous.pixels;
+    }
+
+    // Draw the new frame right away
+    if let Some(ctx) = repaint_context.get() {
+        ctx.request_repaint();
+    }
+
+    Ok(gst::FlowSuccess::Ok)
+}
+
+/// Owns the GStreamer pipeline and exposes playback controls.
+///
+/// The player does not draw anything by itself, see [`crate::VideoPlayer`]
+/// for the egui widget built on top of it.
+pub struct MediaPlayer {
+    pipeline: gst::Element,                      // The GStreamer playbin pipeline
+    appsink: gst_app::AppSink,                   // Sink element that receives video frames
+    duration: Option<gst::ClockTime>,            // Total duration of the current media
+    position: Option<gst::ClockTime>,            // Current playback position
+    video_frame: Arc<Mutex<Option<VideoFrame>>>, // Current video frame data
+    frame_sequence: Arc<AtomicU64>,              // Sequence number of the newest frame
+    _bus_watch: BusWatchGuard,                   // Watch for GStreamer bus messages
+    main_context: glib::MainContext,             // GLib main context for event processing
+    events_rx: mpsc::Receiver<PlayerEvent>,      // Events forwarded by the bus watch
+    pending_events: VecDeque<PlayerEvent>,       // Events not yet taken by the host
+    missing_plugins: Vec<MissingPlugin>,         // Plugins the current media needs but lacks
+    volume: f64,                                 // Playback volume (0.0 to 1.0)
+    muted: bool,                                 // Whether audio output is muted
+    eos_action: Arc<Mutex<EosAction>>,           // What happens at the end of the media
+    at_eos: bool,                                // The end was reached and nothing rewound yet
+    rate: Arc<Mutex<f64>>,                       // Playback rate, 1.0 is normal speed
+    seek_mode: SeekMode,                         // Precision of seeks made with seek()
+    reverse_support: ReverseSupport,             // How well the current media plays backwards
+    loop_a: Option<gst::ClockTime>,              // Start marker of the A-B loop
+    ab_loop: Arc<Mutex<Option<(gst::ClockTime, gst::ClockTime)>>>, // Active A-B loop
+    next_uri: Arc<Mutex<Option<String>>>,        // Media played gaplessly after the current one
+    switched_uri: Arc<Mutex<Option<String>>>, // Queued media switched to, reported when it starts
+    subtitle_uri: Option<String>,             // External subtitles for the media loaded next
+    wants_playing: bool,                      // Playback was asked for, buffering may hold it back
+    buffering: Option<i32>, // Buffer fill level in percent while playback waits for it
+    pipeline_live: bool,    // The pipeline's source is live, it neither prerolls nor buffers
+    is_live: bool,          // The media is treated as live, which cannot be seeked
+    uri: Option<String>,    // URI of the current media, opened again when reconnecting
+    rtsp: Arc<Mutex<RtspSettings>>, // How RTSP streams are received
+    retry_policy: RetryPolicy, // How a failed network stream is opened again
+    retry_attempt: u32,     // Attempts made since the stream last delivered data
+    retry_at: Option<Instant>, // When the failed stream is opened again
+    reopened: Option<(Instant, u64)>, // When the stream was reopened, and the frame sequence then
+    resume_position: Option<gst::ClockTime>, // Where a reopened stream continues
+    time_shift: Option<TimeShift>, // Buffer kept behind live streams, if enabled
+    recorder: Option<Recorder>, // Records the live stream for time-shift
+    recorder_failed: bool,  // The stream cannot be recorded and plays directly
+    playout: Arc<Mutex<Option<Ring>>>, // Recording played instead of the stream
+    shift_start: Option<(gst::ClockTime, Option<gst::ClockTime>)>, // Where playback entered the recording
+    seek_window: Option<(gst::ClockTime, gst::ClockTime)>, // Seekable range without a duration
+    repaint_context: Arc<OnceLock<egui::Context>>,         // UI woken up by new frames and messages
+}
+
+impl MediaPlayer {
+    /// Creates a new MediaPlayer instance, initializing the GStreamer pipeline
+    /// and setting up video processing
+    pub fn new() -> Result<Self, PlayerError> {
+        // Initialize GStreamer
+        gst::init().map_err(|e| PlayerError::InitializationError(e.to_string()))?;
+        gst_pbutils::pb_utils_init();
+
+        // Create playbin element (an all-in-one media player pipeline)
+        let pipeline = gst::ElementFactory::make("playbin")
+            .name("playbin")
+            .build()
+            .map_err(|e| PlayerError::GstreamerError(format!("Failed to create playbin: {}", e)))?;
+
+        // Create appsink for receiving video frames
+        // Configure it to receive RGBA video for easy display in Egui
+        let appsink = gst_app::AppSink::builder()
+            .name("videosink")
+            .caps(
+                &gst::Caps::builder("video/x-raw")
+                    .field("format", "RGBA")
+                    .build(),
+            )
+            .build();
+
+        // Create a bin for video conversion
+        // This ensures we get the video format we want (RGBA)
+        let video_bin = gst::Bin::new();
+        let videoconvert = gst::ElementFactory::make("videoconvert")
+            .build()
+            .map_err(|e| PlayerError::GstreamerError(e.to_string()))?;
+
+        video_bin.add(&videoconvert).unwrap();
+        video_bin.add(appsink.upcast_ref::<gst::Element>()).unwrap();
+
+        // Link the converter to the sink
+        videoconvert
+            .link(appsink.upcast_ref::<gst::Element>())
+            .unwrap();
+
+        // Create a ghost pad to expose the videoconvert sink pad on the bin
+        let sink_pad = videoconvert.static_pad("sink").unwrap();
+        let ghost_pad = gst::GhostPad::builder_with_target(&sink_pad)
+            .unwrap()
+            .name("sink")
+            .build();
+        video_bin.add_pad(&ghost_pad).unwrap();
+
+        // Configure the pipeline to use our video processing bin
+        pipeline.set_property("video-sink", &video_bin);
+
+        // Keep the audio pitch when playing faster or slower than normal
+        if let Ok(scaletempo) = gst::ElementFactory::make("scaletempo").build() {
+            pipeline.set_property("audio-filter", &scaletempo);
+        }
+
+        // Set up shared storage for video frames
+        let video_frame = Arc::new(Mutex::new(None));
+        let frame_sequence = Arc::new(AtomicU64::new(0));
+
+        // The UI context to wake up when something happens, set once the player is drawn
+        let repaint_context: Arc<OnceLock<egui::Context>> = Arc::new(OnceLock::new());
+
+        // Configure the appsink to handle incoming video frames. Prerolled frames
+        // are shown too, so seeking and stepping while paused updates the picture.
+        let preroll_frame = Arc::clone(&video_frame);
+        let preroll_sequence = Arc::clone(&frame_sequence);
+        let preroll_context = Arc::clone(&repaint_context);
+        let sample_frame = Arc::clone(&video_frame);
+        let sample_sequence = Arc::clone(&frame_sequence);
+        let sample_context = Arc::clone(&repaint_context);
+        appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_preroll({
+                    let mut spare = Vec::new();
+                    move |appsink| {
+                        let sample = appsink.pull_preroll().map_err(|_| gst::FlowError::Error)?;
+                        store_sample(
+                            &sample,
+                            &preroll_frame,
+                            &mut spare,
+                            &preroll_sequence,
+                            &preroll_context,
+                        )
+                    }
+                })
+                .new_sample({
+                    let mut spare = Vec::new();
+                    move |appsink| {
+                        let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Error)?;
+                        store_sample(
+                            &sample,
+                            &sample_frame,
+                            &mut spare,
+                            &sample_sequence,
+                            &sample_context,
+                        )
+                    }
+                })
+                .build(),
+        );
+
+        // Set up bus watch to handle pipeline messages and forward them as events
+        let (events_tx, events_rx) = mpsc::channel();
+        let eos_action = Arc::new(Mutex::new(EosAction::default()));
+        let eos_action_clone = Arc::clone(&eos_action);
+        let rate = Arc::new(Mutex::new(1.0));
+        let rate_clone = Arc::clone(&rate);
+        let ab_loop = Arc::new(Mutex::new(None));
+        let ab_loop_clone = Arc::clone(&ab_loop);
+        let switched_uri = Arc::new(Mutex::new(None::<String>));
+        let switched_uri_clone = Arc::clone(&switched_uri);
+        let pipeline_weak = pipeline.downgrade();
+        let bus = pipeline.bus().unwrap();
+
+        // The watch only runs when the UI iterates the main context, so wake
+        // the UI up from the posting thread whenever a message arrives
+        let repaint_context_clone = Arc::clone(&repaint_context);
+        bus.set_sync_handler(move |_, _| {
+            if let Some(ctx) = repaint_context_clone.get() {
+                ctx.request_repaint();
+            }
+            gst::BusSyncReply::Pass
+        });
+
+        let bus_watch = bus
+            .add_watch(move |_, msg| {
+                if let Some(pipeline) = pipeline_weak.upgrade() {
+                    let event = match msg.view() {
+                        gst::MessageView::Error(err) => {
+                            let network = pipeline
+                                .property::<Option<String>>("current-uri")
+                                .is_some_and(|uri| is_network_uri(&uri));
+                            Some(PlayerEvent::Error(PlayerError::from_glib(
+                                &err.error(),
+                                err.src().map(|s| s.path_string().to_string()),
+                                err.debug().map(|d| d.to_string()),
+                                network,
+                            )))
+                        }
+                        gst::MessageView::Warning(warning) => Some(PlayerEvent::Warning {
+                            source: warning.src().map(|s| s.path_string().to_string()),
+                            message: warning.error().to_string(),
+                            debug: warning.debug().map(|d| d.to_string()),
+                        }),
+                        gst::MessageView::Eos(_) => {
+                            let rate = *rate_clone.lock().unwrap();
+                            let result = match *eos_action_clone.lock().unwrap() {
+                                EosAction::Stop => pause_pipeline(&pipeline).and_then(|()| {
+                                    seek_after_eos(
+                                        &pipeline,
+                                        rate,
+                                        gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
+                                        LoopRange::None,
+                                    )
+                                }),
+                                // Looping normally ends in SegmentDone, this only happens
+                                // when the loop was enabled after the last segment seek
+                                EosAction::Loop => seek_after_eos(
+                                    &pipeline,
+                                    rate,
+                                    gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
+                                    LoopRange::Whole,
+                                ),
+                                EosAction::HoldLastFrame | EosAction::Next => {
+                                    pause_pipeline(&pipeline)
+                                }
+                            };
+                            if let Err(error) = result {
+                                let _ = events_tx.send(PlayerEvent::Error(error));
+                            }
+                            Some(PlayerEvent::EndOfStream)
+                        }
+                        gst::MessageView::SegmentDone(_) => {
+                            // Start the loop over without flushing, so there is no gap
+                            let range = LoopRange::new(
+                                *eos_action_clone.lock().unwrap(),
+                                *ab_loop_clone.lock().unwrap(),
+                            );
+                            if range == LoopRange::None {
+                                None
+                            } else {
+                                seek_after_eos(
+                                    &pipeline,
+                                    *rate_clone.lock().unwrap(),
+                                    gst::SeekFlags::ACCURATE,
+                                    range,
+                                )
+                                .err()
+                                .map(PlayerEvent::Error)
+                            }
+                        }
+                        gst::MessageView::StateChanged(state) => {
+                            // Only report state changes of the pipeline itself
+                            if state
+                                .src()
+                                .map(|s| {
+                                    s.as_ptr() == pipeline.upcast_ref::<gst::Object>().as_ptr()
+                                })
+                                .unwrap_or(false)
+                            {
+                                Some(PlayerEvent::StateChanged {
+                                    old: state.old(),
+                                    current: state.current(),
+                                    pending: state.pending(),
+                                })
+                            } else {
+                                None
+                            }
+                        }
+                        gst::MessageView::StreamStart(_) => switched_uri_clone
+                            .lock()
+                            .unwrap()
+                            .take()
+                            .map(PlayerEvent::MediaChanged),
+                        gst::MessageView::Buffering(buffering) => {
+                            Some(PlayerEvent::Buffering(buffering.percent()))
+                        }
+                        gst::MessageView::Latency(_) => {
+                            // An element's latency changed, e.g. a live source was added
+                            if let Some(bin) = pipeline.downcast_ref::<gst::Bin>() {
+                                let _ = bin.recalculate_latency();
+                            }
+                            None
+                        }
+                        gst::MessageView::Tag(tag) => Some(PlayerEvent::Tags(tag.tags())),
+                        gst::MessageView::DurationChanged(_) => Some(PlayerEvent::DurationChanged),
+                        gst::MessageView::StreamCollection(collection) => Some(
+                            PlayerEvent::StreamCollection(collection.stream_collection()),
+                        ),
+                        gst::MessageView::Element(_) => {
+                            MissingPlugin::from_message(msg).map(PlayerEvent::MissingPlugin)
+                        }
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        let _ = events_tx.send(event);
+                    }
+                }
+                glib::ControlFlow::Continue
+            })
+            .expect("Failed to add bus watch");
+
+        // Playbin asks for the next URI while the current media is still playing, so
+        // switching here avoids a gap. The switch is reported once the new stream starts.
+        let next_uri = Arc::new(Mutex::new(None::<String>));
+        let next_uri_clone = Arc::clone(&next_uri);
+        let about_to_finish_action = Arc::clone(&eos_action);
+        let about_to_finish_uri = Arc::clone(&switched_uri);
+        pipeline.connect("about-to-finish", false, move |values| {
+            if *about_to_finish_action.lock().unwrap() != EosAction::Next {
+                return None;
+            }
+            if let Some(uri) = next_uri_clone.lock().unwrap().take() {
+                let playbin = values[0].get::<gst::Element>().unwrap();
+                playbin.set_property("uri", &uri);
+                *about_to_finish_uri.lock().unwrap() = Some(uri);
+            }
+            None
+        });
+
+        // Configure network sources as playbin creates them for new media
+        let rtsp = Arc::new(Mutex::new(RtspSettings::default()));
+        let rtsp_clone = Arc::clone(&rtsp);
+        let playout: Arc<Mutex<Option<Ring>>> = Arc::new(Mutex::new(None));
+        let playout_clone = Arc::clone(&playout);
+        pipeline.connect("source-setup", false, move |values| {
+            let source = values[1].get::<gst::Element>().unwrap();
+            rtsp_clone.lock().unwrap().apply(&source);
+            // While time-shifting, the pipeline plays the recording from an appsrc
+            if let (Some(ring), Some(appsrc)) = (
+                playout_clone.lock().unwrap().as_ref(),
+                source.downcast_ref::<gst_app::AppSrc>(),
+            ) {
+                ring.attach(appsrc);
+            }
+            None
+        });
+
+        Ok(MediaPlayer {
+            pipeline,
+            appsink,
+            duration: None,
+            position: None,
+            video_frame,
+            frame_sequence,
+            _bus_watch: bus_watch,
+            main_context: MainContext::default(),
+            events_rx,
+            pending_events: VecDeque::new(),
+            missing_plugins: Vec::new(),
+            volume: 1.0,
+            muted: false,
+            eos_action,
+            at_eos: false,
+            rate,
+            seek_mode: SeekMode::default(),
+            reverse_support: ReverseSupport::default(),
+            loop_a: None,
+            ab_loop,
+            next_uri,
+            switched_uri,
+            subtitle_uri: None,
+            wants_playing: false,
+            buffering: None,
+            pipeline_live: false,
+            is_live: false,
+            uri: None,
+            rtsp,
+            retry_policy: RetryPolicy::default(),
+            retry_attempt: 0,
+            retry_at: None,
+            reopened: None,
+            resume_position: None,
+            time_shift: None,
+            recorder: None,
+            recorder_failed: false,
+            playout,
+            shift_start: None,
+            seek_window: None,
+            repaint_context,
+        })
+    }
+
+    /// Loads a video file from the given path and prerolls it in the paused state
+    pub fn load_file(&mut self, path: PathBuf) -> Result<(), PlayerError> {
+        self.load(&MediaLocation::from_path(path)?)
+    }
+
+    /// Loads media from a URI, e.g. `https://` or `rtsp://`, and prerolls it in the paused state
+    pub fn load_uri(&mut self, uri: &str) -> Result<(), PlayerError> {
+        self.load(&MediaLocation::from_uri(uri)?)
+    }
+
+    /// Loads media from a file or URI and prerolls it in the paused state
+    pub fn load(&mut self, location: &MediaLocation) -> Result<(), PlayerError> {
+        self.stop()?;
+        self.pipeline
+            .set_property("suburi", self.subtitle_uri.take());
+        self.retry_attempt = 0;
+        self.open_uri(location.uri())
+    }
+
+    /// Opens a URI in the stopped pipeline, keeping the subtitles, and prerolls it
+    fn open_uri(&mut self, uri: &str) -> Result<(), PlayerError> {
+        self.pipeline.set_property("uri", uri);
+        self.uri = Some(uri.to_string());
+        // A gapless switch that has not started yet is replaced by this media
+        *self.switched_uri.lock().unwrap() = None;
+        *self.video_frame.lock().unwrap() = None;
+        self.reset_media_state();
+        self.pause()?;
+        Ok(())
+    }
+
+    /// Sets an external subtitle file shown with the media loaded next. Playbin only
+    /// takes subtitles when media is loaded, so this has to be called before loading.
+    pub fn set_subtitle_file(&mut self, path: Option<&Path>) -> Result<(), PlayerError> {
+        self.subtitle_uri = match path {
+            Some(path) => Some(MediaLocation::from_path(path)?.uri().to_string()),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Sets how `rtsp://` streams are received. Takes effect when media is loaded.
+    pub fn set_rtsp_settings(&mut self, settings: RtspSettings) {
+        *self.rtsp.lock().unwrap() = settings;
+    }
+
+    /// Gets how `rtsp://` streams are received
+    pub fn rtsp_settings(&self) -> RtspSettings {
+        self.rtsp.lock().unwrap().clone()
+    }
+
+    /// Queues the media played gaplessly once the current one finishes, when the
+    /// EOS action is [`EosAction::Next`]. `None` lets playback end after the current media.
+    pub fn queue_next(&mut self, location: Option<&MediaLocation>) {
+        *self.next_uri.lock().unwrap() = location.map(|location| location.uri().to_string());
+    }
+
+    /// Forgets everything known about the previous media
+    fn reset_media_state(&mut self) {
+        self.duration = None;
+        self.position = Some(gst::ClockTime::ZERO);
+        self.missing_plugins.clear();
+        *self.rate.lock().unwrap() = 1.0;
+        self.reverse_support = ReverseSupport::Unknown;
+        self.loop_a = None;
+        *self.ab_loop.lock().unwrap() = None;
+        self.at_eos = false;
+        self.pipeline_live = false;
+        self.is_live = false;
+        self.end_time_shift();
+        self.recorder_failed = false;
+        self.seek_window = None;
+    }
+
+    /// Sets the playback volume (0.0 to 1.0)
+    pub fn set_volume(&mut self, volume: f64) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.pipeline.set_property("volume", self.volume);
+    }
+
+    /// Gets the playback volume (0.0 to 1.0)
+    pub fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    /// Mutes or unmutes audio output without touching the volume
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.pipeline.set_property("mute", muted);
+    }
+
+    /// Checks if audio output is muted
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Sets what happens when the end of the media is reached
+    pub fn set_eos_action(&mut self, action: EosAction) -> Result<(), PlayerError> {
+        let previous = std::mem::replace(&mut *self.eos_action.lock().unwrap(), action);
+        if previous == action
+            || self.is_live
+            || self.ab_loop.lock().unwrap().is_some()
+            || !matches!(self.get_state(), gst::State::Paused | gst::State::Playing)
+        {
+            return Ok(());
+        }
+
+        // Enter or leave segment mode at the current position
+        if previous == EosAction::Loop || action == EosAction::Loop {
+            let position = self.position.unwrap_or(gst::ClockTime::ZERO);
+            self.seek_to(position, SeekMode::Accurate)?;
+        }
+        Ok(())
+    }
+
+    /// Gets what happens when the end of the media is reached
+    pub fn eos_action(&self) -> EosAction {
+        *self.eos_action.lock().unwrap()
+    }
+
+    /// Gets the current state of the pipeline
+    pub fn get_state(&self) -> gst::State {
+        self.pipeline.current_state()
+    }
+
+    /// Gets the current playback position
+    pub fn position(&self) -> Option<gst::ClockTime> {
+        self.position
+    }
+
+    /// Gets the total duration of the current media, once known
+    pub fn duration(&self) -> Option<gst::ClockTime> {
+        self.duration
+    }
+
+    /// Sets the pipeline to NULL state (completely stopped)
+    pub fn set_null(&mut self) -> Result<(), PlayerError> {
+        self.pipeline
+            .set_state(gst::State::Null)
+            .map_err(|e| PlayerError::state_change(format!("Failed to set null: {}", e)))?;
+        Ok(())
+    }
+
+    /// Starts or resumes playback. While buffering, playback starts once the buffer is full.
+    pub fn play(&mut self) -> Result<(), PlayerError> {
+        self.wants_playing = true;
+        if self.buffering.is_some() {
+            return Ok(());
+        }
+        let ret = self
+            .pipeline
+            .set_state(gst::State::Playing)
+            .map_err(|e| PlayerError::state_change(format!("Failed to play: {}", e)))?;
+        self.pipeline_live |= ret == gst::StateChangeSuccess::NoPreroll;
+        self.is_live |= self.pipeline_live;
+        Ok(())
+    }
+
+    /// Pauses playback
+    pub fn pause(&mut self) -> Result<(), PlayerError> {
+        self.wants_playing = false;
+        let ret = self
+            .pipeline
+            .set_state(gst::State::Paused)
+            .map_err(|e| PlayerError::state_change(format!("Failed to pause: {}", e)))?;
+        // Live sources do not preroll, they only produce data while playing
+        self.pipeline_live |= ret == gst::StateChangeSuccess::NoPreroll;
+        self.is_live |= self.pipeline_live;
+        Ok(())
+    }
+
+    /// Stops playback and resets position
+    pub fn stop(&mut self) -> Result<(), PlayerError> {
+        self.wants_playing = false;
+        self.buffering = None;
+        self.retry_at = None;
+        self.reopened = None;
+        self.resume_position = None;
+        let time_shifted = self.end_time_shift();
+        self.pipeline
+            .set_state(gst::State::Ready)
+            .map_err(|e| PlayerError::state_change(format!("Failed to stop: {}", e)))?;
+        if time_shifted {
+            // Play the stream itself again next time
+            self.pipeline.set_property("uri", self.uri.as_deref());
+        }
+        self.position = Some(gst::ClockTime::ZERO);
+        Ok(())
+    }
+
+    /// Toggles between playing and paused states
+    pub fn toggle_playback(&mut self) -> Result<(), PlayerError> {
+        match self.get_state() {
+            gst::State::Playing => self.pause(),
+            // Held back by buffering, to the user it is playing
+            gst::State::Paused if self.wants_playing => self.pause(),
+            gst::State::Paused | gst::State::Ready => {
+                // Playing again after the end starts over. Live streams cannot be
+                // rewound, they continue wherever the source is now.
+                if self.at_eos && !self.is_live {
+                    self.seek_at_rate(
+                        self.rate(),
+                        gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
+                        None,
+                    )
+                    .map_err(|e| PlayerError::GstreamerError(format!("Failed to seek: {}", e)))?;
+                }
+                self.play()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the egui context to repaint when a new frame or bus message arrives.
+    /// Only the first context is kept, the widget calls this on every frame.
+    pub fn set_repaint_context(&self, ctx: &egui::Context) {
+        if self.repaint_context.get().is_none() {
+            let _ = self.repaint_context.set(ctx.clone());
+        }
+    }
+
+    /// Processes pending GStreamer bus messages and refreshes position and duration.
+    /// Call this once per UI frame.
+    pub fn update(&mut self) {
+        while self.main_context.iteration(false) {}
+        while let Ok(event) = self.events_rx.try_recv() {
+            self.handle_event(&event);
+            self.push_event(event);
+        }
+        self.retry_if_due();
+        self.check_recovered();
+        self.update_time_shift();
+        self.update_position();
+    }
+
+    /// Queues an event for the host, dropping the oldest one when the queue is full
+    fn push_event(&mut self, event: PlayerEvent) {
+        if self.pending_events.len() == MAX_PENDING_EVENTS {
+            self.pending_events.pop_front();
+        }
+        self.pending_events.push_back(event);
+    }
+
+    /// Hands the error of a failed call to the host as an event, for callers
+    /// such as the widget controls that have nobody to return it to
+    pub fn report_error(&mut self, error: PlayerError) {
+        self.push_event(PlayerEvent::Error(error));
+    }
+
+    /// Takes all events received since the last call, oldest first
+    pub fn poll_events(&mut self) -> impl Iterator<Item = PlayerEvent> + '_ {
+        self.pending_events.drain(..)
+    }
+
+    /// Updates the player state from an event before it is handed to the host
+    fn handle_event(&mut self, event: &PlayerEvent) {
+        match event {
+            PlayerEvent::DurationChanged => self.duration = None,
+            PlayerEvent::EndOfStream => {
+                self.at_eos = true;
+                // A live stream only ends when the server drops it
+                let retrying = self.is_live && self.schedule_retry();
+                // Everything but looping paused the pipeline
+                if self.eos_action() != EosAction::Loop && !retrying {
+                    self.wants_playing = false;
+                }
+            }
+            // Missing media and refused access fail the same way every time, so only
+            // network failures are retried
+            PlayerEvent::Error(PlayerError::NetworkFailure(_)) => {
+                self.schedule_retry();
+            }
+            PlayerEvent::Buffering(percent) => self.handle_buffering(*percent),
+            PlayerEvent::MediaChanged(uri) => {
+                self.uri = Some(uri.clone());
+                self.reset_media_state();
+                self.update_live();
+            }
+            PlayerEvent::StateChanged {
+                current: gst::State::Playing,
+                ..
+            } => {
+                self.update_live();
+            }
+            PlayerEvent::StateChanged {
+                old,
+                current: gst::State::Paused,
+                ..
+            } => {
+                self.update_live();
+                if self.reverse_support == ReverseSupport::Unknown {
+                    self.reverse_support = if self.is_live {
+                        ReverseSupport::Unsupported
+                    } else {
+                        self.detect_reverse_support()
+                    };
+                }
+                // A reopened stream continues where it failed. Looping needs a segment
+                // seek once prerolled, before the end is reached.
+                if *old == gst::State::Ready && !self.is_live {
+                    let resume = self.resume_position.take();
+                    if resume.is_some() || self.eos_action() == EosAction::Loop {
+                        let position = resume.unwrap_or(gst::ClockTime::ZERO);
+                        if let Err(e) = self.seek_to(position, SeekMode::Accurate) {
+                            self.report_error(e);
+                        }
+                    }
+                }
+            }
+            PlayerEvent::MissingPlugin(missing) => {
+                if !self.missing_plugins.contains(missing) {
+                    self.missing_plugins.push(missing.clone());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Plans to open the current network stream again after it failed, unless the
+    /// retry policy is used up. Returns whether an attempt is pending.
+    fn schedule_retry(&mut self) -> bool {
+        if self.retry_at.is_some() {
+            return true;
+        }
+        let network = self.uri.as_deref().is_some_and(is_network_uri);
+        if !network || self.retry_attempt >= self.retry_policy.max_attempts {
+            return false;
+        }
+        self.retry_attempt += 1;
+        self.retry_at = Some(Instant::now() + self.retry_policy.delay(self.retry_attempt));
+        // Streams without a duration are opened at the live edge, others where they stopped
+        if self.duration.is_some() {
+            self.resume_position = self.resume_position.or(self
+                .position
+                .filter(|position| *position > gst::ClockTime::ZERO));
+        }
+        true
+    }
+
+    /// Opens the failed stream again once the retry delay has passed,
+    /// resuming playback if it was playing
+    fn retry_if_due(&mut self) {
+        let Some(at) = self.retry_at else {
+            return;
+        };
+        let now = Instant::now();
+        if now < at {
+            // Nothing else may wake the UI up while the stream is down
+            if let Some(ctx) = self.repaint_context.get() {
+                ctx.request_repaint_after(at - now);
+            }
+            return;
+        }
+        let Some(uri) = self.uri.clone() else {
+            self.retry_at = None;
+            return;
+        };
+        let resume = self.wants_playing;
+        let position = self.resume_position;
+        self.push_event(PlayerEvent::Reconnecting {
+            attempt: self.retry_attempt,
+            max_attempts: self.retry_policy.max_attempts,
+        });
+        let result = self.stop().and_then(|()| {
+            self.resume_position = position;
+            self.open_uri(&uri)
+        });
+        let result = result.and_then(|()| if resume { self.play() } else { Ok(()) });
+        match result {
+            Ok(()) => {
+                let sequence = self.frame_sequence.load(Ordering::Relaxed);
+                self.reopened = Some((Instant::now(), sequence));
+            }
+            Err(e) => self.report_error(e),
+        }
+    }
+
+    /// Starts over with a fresh set of attempts once a reopened stream really delivers:
+    /// a new video frame arrived, or it played for a while without failing again.
+    /// Reaching the playing state is not enough, live sources get there without data.
+    fn check_recovered(&mut self) {
+        let Some((opened, sequence)) = self.reopened else {
+            return;
+        };
+        if self.retry_at.is_some() {
+            // Failed again before recovering
+            self.reopened = None;
+            return;
+        }
+        let new_frame = self.frame_sequence.load(Ordering::Relaxed) > sequence;
+        let stable = opened.elapsed() >= RECOVERY_PERIOD && self.get_state() == gst::State::Playing;
+        if new_frame || stable {
+            self.retry_attempt = 0;
+            self.reopened = None;
+        }
+    }
+
+    /// Sets how a network stream that failed is opened again
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Gets how a network stream that failed is opened again
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Gets the attempt the player waits to make and the time left until then,
+    /// while a failed network stream is about to be opened again
+    pub fn pending_retry(&self) -> Option<(u32, Duration)> {
+        let at = self.retry_at?;
+        Some((
+            self.retry_attempt,
+            at.saturating_duration_since(Instant::now()),
+        ))
+    }
+
+    /// Pauses playback while the buffer fills up and resumes once it is full,
+    /// so network streams do not stutter
+    fn handle_buffering(&mut self, percent: i32) {
+        // Pausing a live source would only drop data, it keeps producing in real time.
+        // Streams that are only live for the UI, like live HLS, still buffer.
+        if self.pipeline_live {
+            self.buffering = None;
+            return;
+        }
+        let target = if percent < 100 {
+            self.buffering = Some(percent);
+            gst::State::Paused
+        } else {
+            self.buffering = None;
+            gst::State::Playing
+        };
+        if self.wants_playing && self.get_state() != target {
+            if let Err(e) = self.pipeline.set_state(target) {
+                self.report_error(PlayerError::state_change(format!(
+                    "Failed to change state while buffering: {}",
+                    e
+                )));
+            }
+        }
+    }
+
+    /// Gets the buffer fill level in percent while playback waits for the buffer to fill,
+    /// `None` when not buffering
+    pub fn buffering_percent(&self) -> Option<i32> {
+        self.buffering
+    }
+
+    /// Checks if the media is a live source such as a camera, RTSP or live HLS stream.
+    /// Live media has no duration and cannot be seeked or played at another rate.
+    pub fn is_live(&self) -> bool {
+        self.is_live
+    }
+
+    /// Asks the pipeline whether it is live, which is only known once data flows.
+    /// Streams without a duration that cannot be seeked, such as live HLS, are live
+    /// for the player even though their sources are not live in GStreamer terms.
+    fn update_live(&mut self) {
+        let mut query = gst::query::Latency::new();
+        self.pipeline_live |= self.pipeline.query(&mut query) && query.result().0;
+        self.is_live |= self.pipeline_live || self.query_unseekable();
+    }
+
+    /// Checks for media without a duration that cannot be seeked
+    fn query_unseekable(&self) -> bool {
+        if self.pipeline.query_duration::<gst::ClockTime>().is_some() {
+            return false;
+        }
+        let mut query = gst::query::Seeking::new(gst::Format::Time);
+        self.pipeline.query(&mut query) && !query.result().0
+    }
+
+    /// Gets the plugins the current media needs but which are not installed
+    pub fn missing_plugins(&self) -> &[MissingPlugin] {
+        &self.missing_plugins
+    }
+
+    /// Updates the current playback position and duration
+    fn update_position(&mut self) {
+        if let Some(position) = self.pipeline.query_position::<gst::ClockTime>() {
+            self.position = Some(match &mut self.shift_start {
+                // The recording time playback restarted at, plus how far it got since.
+                // The pipeline position is only meaningful once it prerolled.
+                Some((start, None)) if self.pipeline.pending_state() != gst::State::VoidPending => {
+                    *start
+                }
+                Some((start, origin)) => {
+                    *start + position.saturating_sub(*origin.get_or_insert(position))
+                }
+                None => position,
+            });
+        }
+
+        if self.duration.is_none() {
+            if let Some(duration) = self.pipeline.query_duration::<gst::ClockTime>() {
+                self.duration = Some(duration);
+            }
+        }
+        self.update_seek_window();
+    }
+
+    /// Follows the seekable range of media without a duration. It is either the part
+    /// of a live stream kept in the time-shift recording or reported by the pipeline,
+    /// e.g. for HLS with a DVR window.
+    fn update_seek_window(&mut self) {
+        self.seek_window = None;
+        if self.shift_start.is_some() {
+            self.seek_window = self.playout_ring().and_then(|ring| ring.window());
+            return;
+        }
+        if self.duration.is_some()
+            || self.is_live
+            || !matches!(self.get_state(), gst::State::Paused | gst::State::Playing)
+        {
+            return;
+        }
+
+        let mut query = gst::query::Seeking::new(gst::Format::Time);
+        if self.pipeline.query(&mut query) {
+            if let (
+                true,
+                gst::GenericFormattedValue::Time(Some(start)),
+                gst::GenericFormattedValue::Time(Some(end)),
+            ) = query.result()
+            {
+                if end > start {
+                    self.seek_window = Some((start, end));
+                }
+            }
+        }
+    }
+
+    /// Gets the seekable range of media without a duration, such as a live stream
+    /// kept in the time-shift recording
+    pub fn seek_window(&self) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        self.seek_window
+    }
+
+    /// Checks if playback runs behind the live edge of the seekable window
+    pub fn is_behind_live(&self) -> bool {
+        match (self.seek_window, self.position) {
+            (Some((_, end)), Some(position)) => end.saturating_sub(position) > LIVE_EDGE_TOLERANCE,
+            _ => false,
+        }
+    }
+
+    /// Catches up with the live edge of the seekable window and plays from there
+    pub fn go_to_live(&mut self) -> Result<(), PlayerError> {
+        if let Some((_, end)) = self.seek_window {
+            self.seek_to(end, SeekMode::Keyframe)?;
+        }
+        self.play()
+    }
+
+    /// Records live streams so they can be paused, rewound and caught up again, or
+    /// stops with `None`. Live streams that are playing are switched right away.
+    pub fn set_time_shift(&mut self, time_shift: Option<TimeShift>) -> Result<(), PlayerError> {
+        self.time_shift = time_shift;
+        if time_shift.is_some() {
+            return Ok(());
+        }
+        if self.shift_start.is_none() {
+            self.end_time_shift();
+            return Ok(());
+        }
+        // Go back to playing the stream itself, at the live edge
+        let resume = self.wants_playing;
+        self.stop()?;
+        if let Some(uri) = self.uri.clone() {
+            self.open_uri(&uri)?;
+            if resume {
+                self.play()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the time-shift recording kept behind live streams, if enabled
+    pub fn time_shift(&self) -> Option<TimeShift> {
+        self.time_shift
+    }
+
+    /// Checks if a live stream is played from the time-shift recording
+    pub fn is_time_shifting(&self) -> bool {
+        self.shift_start.is_some()
+    }
+
+    /// Gets the recording played instead of the stream, while time-shifting
+    fn playout_ring(&self) -> Option<Ring> {
+        self.playout.lock().unwrap().clone()
+    }
+
+    /// Records the live stream while time-shift is enabled, and switches playback over
+    /// to the recording once it holds a keyframe
+    fn update_time_shift(&mut self) {
+        if let Some(recorder) = &self.recorder {
+            let error = recorder.take_error();
+            if let Some(error) = error {
+                if let Some(ring) = self.playout_ring() {
+                    // Play what was recorded, then fail like the stream itself would
+                    ring.end();
+                    self.recorder = None;
+                    let event = PlayerEvent::Error(error);
+                    self.handle_event(&event);
+                    self.push_event(event);
+                } else {
+                    self.give_up_time_shift(Some(error.to_string()));
+                }
+                return;
+            }
+            if recorder.is_stalled() {
+                self.give_up_time_shift(None);
+                return;
+            }
+        }
+
+        let Some(time_shift) = self.time_shift else {
+            return;
+        };
+        if !self.is_live
+            || self.recorder_failed
+            || self.shift_start.is_some()
+            || self.retry_at.is_some()
+            || !matches!(self.get_state(), gst::State::Paused | gst::State::Playing)
+        {
+            return;
+        }
+        match &self.recorder {
+            None => {
+                let Some(uri) = self.uri.clone() else {
+                    return;
+                };
+                match Recorder::start(&uri, &time_shift, self.rtsp_settings()) {
+                    Ok(recorder) => self.recorder = Some(recorder),
+                    Err(e) => self.give_up_time_shift(Some(e.to_string())),
+                }
+            }
+            Some(recorder) => {
+                let Some((_, edge)) = recorder.ring().window() else {
+                    return;
+                };
+                *self.playout.lock().unwrap() = Some(recorder.ring().clone());
+                if let Err(e) = self.restart_playout(edge) {
+                    self.report_error(e);
+                }
+            }
+        }
+    }
+
+    /// Stops recording a stream that cannot be recorded, it keeps playing directly
+    fn give_up_time_shift(&mut self, debug: Option<String>) {
+        self.recorder = None;
+        self.recorder_failed = true;
+        self.push_event(PlayerEvent::Warning {
+            source: None,
+            message: "This stream cannot be time-shifted".to_string(),
+            debug,
+        });
+    }
+
+    /// Plays the recording from the last keyframe at or before `target`. The pipeline
+    /// is started over, the recording cannot be seeked in place.
+    fn restart_playout(&mut self, target: gst::ClockTime) -> Result<(), PlayerError> {
+        let Some(start) = self.playout_ring().and_then(|ring| ring.rewind_to(target)) else {
+            return Ok(());
+        };
+        self.pipeline
+            .set_state(gst::State::Ready)
+            .map_err(|e| PlayerError::state_change(format!("Failed to seek: {}", e)))?;
+        self.pipeline.set_property("uri", "appsrc://");
+        self.shift_start = Some((start, None));
+        self.position = Some(start);
+        self.at_eos = false;
+        let state = if self.wants_playing {
+            gst::State::Playing
+        } else {
+            gst::State::Paused
+        };
+        self.pipeline
+            .set_state(state)
+            .map_err(|e| PlayerError::state_change(format!("Failed to seek: {}", e)))?;
+        Ok(())
+    }
+
+    /// Stops recording and playing the recording. Returns whether the pipeline played
+    /// the recording, and has to be pointed at the stream again.
+    fn end_time_shift(&mut self) -> bool {
+        self.recorder = None;
+        *self.playout.lock().unwrap() = None;
+        self.shift_start.take().is_some()
+    }
+
+    /// Seeks to a specific position (0.0 to 1.0) in the video using the configured seek mode
+    pub fn seek(&mut self, position: f64) -> Result<(), PlayerError> {
+        if let Some(position) = self.fraction_to_time(position) {
+            self.seek_to(position, self.seek_mode)?;
+        }
+        Ok(())
+    }
+
+    /// Seeks to a specific time in the video
+    pub fn seek_to(&mut self, position: gst::ClockTime, mode: SeekMode) -> Result<(), PlayerError> {
+        if self.shift_start.is_some() {
+            // The recording is only entered at keyframes
+            return self.restart_playout(position);
+        }
+        self.seek_at_rate(
+            self.rate(),
+            gst::SeekFlags::FLUSH | mode.flags(),
+            Some(position),
+        )
+        .map_err(|e| PlayerError::GstreamerError(format!("Failed to seek: {}", e)))?;
+        self.position = Some(position);
+        Ok(())
+    }
+
+    /// Seeks in the direction of `rate`, keeping track of how well reverse playback works
+    fn seek_at_rate(
+        &mut self,
+        rate: f64,
+        flags: gst::SeekFlags,
+        position: Option<gst::ClockTime>,
+    ) -> Result<(), glib::BoolError> {
+        if self.is_live {
+            return Err(glib::bool_error!("Live streams cannot be seeked"));
+        }
+        let keyframes_only = self.reverse_support == ReverseSupport::KeyframesOnly;
+        let range = LoopRange::new(self.eos_action(), *self.ab_loop.lock().unwrap());
+        let position = match range {
+            LoopRange::Between(a, b) => position.map(|position| position.clamp(a, b)),
+            LoopRange::None | LoopRange::Whole => position,
+        };
+        self.at_eos = false;
+        match seek_in_direction(&self.pipeline, rate, flags, position, range, keyframes_only) {
+            Ok(used_keyframes) => {
+                if rate < 0.0 {
+                    self.reverse_support = if used_keyframes {
+                        ReverseSupport::KeyframesOnly
+                    } else {
+                        ReverseSupport::Full
+                    };
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if rate < 0.0 {
+                    self.reverse_support = ReverseSupport::Unsupported;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Converts a position from 0.0 to 1.0 into a time, once the duration or the
+    /// seekable window is known
+    pub fn fraction_to_time(&self, position: f64) -> Option<gst::ClockTime> {
+        let (start, end) = match self.duration {
+            Some(duration) => (gst::ClockTime::ZERO, duration),
+            None => self.seek_window?,
+        };
+        let length = end.saturating_sub(start);
+        Some(
+            start
+                + gst::ClockTime::from_nseconds(
+                    (position.clamp(0.0, 1.0) * length.nseconds() as f64) as u64,
+                ),
+        )
+    }
+
+    /// Gets the seek mode used by [`MediaPlayer::seek`]
+    pub fn seek_mode(&self) -> SeekMode {
+        self.seek_mode
+    }
+
+    /// Sets the seek mode used by [`MediaPlayer::seek`]
+    pub fn set_seek_mode(&mut self, mode: SeekMode) {
+        self.seek_mode = mode;
+    }
+
+    /// Sets the A marker of the A-B loop at the current position
+    pub fn set_loop_a(&mut self) -> Result<(), PlayerError> {
+        let Some(position) = self.position else {
+            return Ok(());
+        };
+        let (_, b) = self.loop_markers();
+        match b {
+            Some(b) if b > position => self.start_ab_loop(position, b)?,
+            // A moved past B, so B has to be set again
+            Some(_) => self.clear_loop()?,
+            None => (),
+        }
+        self.loop_a = Some(position);
+        Ok(())
+    }
+
+    /// Sets the B marker of the A-B loop at the current position and starts looping
+    pub fn set_loop_b(&mut self) -> Result<(), PlayerError> {
+        let Some(position) = self.position else {
+            return Ok(());
+        };
+        let a = self.loop_a.unwrap_or(gst::ClockTime::ZERO);
+        if position <= a {
+            return Ok(());
+        }
+        self.loop_a = Some(a);
+        self.start_ab_loop(a, position)
+    }
+
+    /// Removes both A-B loop markers and continues playing normally
+    pub fn clear_loop(&mut self) -> Result<(), PlayerError> {
+        self.loop_a = None;
+        let was_looping = self.ab_loop.lock().unwrap().take().is_some();
+        if was_looping {
+            // A flushing seek outside the A-B segment leaves it, looping the whole file if set
+            let position = self.position.unwrap_or(gst::ClockTime::ZERO);
+            self.seek_to(position, SeekMode::Accurate)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the A and B markers of the A-B loop
+    pub fn loop_markers(&self) -> (Option<gst::ClockTime>, Option<gst::ClockTime>) {
+        match *self.ab_loop.lock().unwrap() {
+            Some((a, b)) => (Some(a), Some(b)),
+            None => (self.loop_a, None),
+        }
+    }
+
+    /// Starts looping between A and B with a segment seek to the start of the loop
+    fn start_ab_loop(&mut self, a: gst::ClockTime, b: gst::ClockTime) -> Result<(), PlayerError> {
+        if self.is_live {
+            return Err(PlayerError::GstreamerError(
+                "Live streams cannot be looped".to_string(),
+            ));
+        }
+        *self.ab_loop.lock().unwrap() = Some((a, b));
+        let start = if self.is_reverse() { b } else { a };
+        self.seek_to(start, SeekMode::Accurate)
+    }
+
+    /// Shows the next frame, pausing playback first if needed
+    pub fn step_forward(&mut self) -> Result<(), PlayerError> {
+        if self.get_state() == gst::State::Playing {
+            self.pause()?;
+        }
+        let step = gst::event::Step::new(gst::format::Buffers::ONE, 1.0, true, false);
+        if !self.appsink.send_event(step) {
+            return Err(PlayerError::GstreamerError(
+                "Failed to step forward".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Shows the previous frame, pausing playback first if needed.
+    /// GStreamer can only step forward, so this seeks accurately to the previous timestamp.
+    pub fn step_backward(&mut self) -> Result<(), PlayerError> {
+        if self.is_live {
+            return Err(PlayerError::GstreamerError(
+                "Live streams cannot be stepped backwards".to_string(),
+            ));
+        }
+        if self.get_state() == gst::State::Playing {
+            self.pause()?;
+        }
+        match self.with_frame(|frame| frame.and_then(VideoFrame::previous_pts)) {
+            Some(previous) => self.seek_to(previous, SeekMode::Accurate),
+            None => Ok(()),
+        }
+    }
+
+    /// Gets the number of the frame currently shown
+    pub fn frame_number(&self) -> Option<u64> {
+        self.with_frame(|frame| frame.and_then(VideoFrame::number))
+    }
+
+    /// Gets the playback rate, 1.0 is normal speed
+    pub fn rate(&self) -> f64 {
+        *self.rate.lock().unwrap()
+    }
+
+    /// Sets the playback rate, negative rates play backwards. The speed is
+    /// clamped to [`MIN_RATE`]..=[`MAX_RATE`] in either direction.
+    pub fn set_rate(&mut self, rate: f64) -> Result<(), PlayerError> {
+        let rate = rate.abs().clamp(MIN_RATE, MAX_RATE).copysign(rate);
+        if self.is_live && rate != 1.0 {
+            return Err(PlayerError::GstreamerError(
+                "Live streams can only play at normal speed".to_string(),
+            ));
+        }
+        let previous_rate = std::mem::replace(&mut *self.rate.lock().unwrap(), rate);
+        if !matches!(self.get_state(), gst::State::Paused | gst::State::Playing) {
+            return Ok(());
+        }
+
+        // Change the rate without flushing if the pipeline supports it. That
+        // cannot change direction, so otherwise seek to the current position
+        // at the new rate.
+        if previous_rate.is_sign_negative() == rate.is_sign_negative()
+            && self
+                .pipeline
+                .seek(
+                    rate,
+                    gst::SeekFlags::INSTANT_RATE_CHANGE,
+                    gst::SeekType::None,
+                    gst::ClockTime::NONE,
+                    gst::SeekType::None,
+                    gst::ClockTime::NONE,
+                )
+                .is_ok()
+        {
+            return Ok(());
+        }
+        let position = self
+            .pipeline
+            .query_position::<gst::ClockTime>()
+            .or(self.position)
+            .unwrap_or(gst::ClockTime::ZERO);
+        self.seek_at_rate(
+            rate,
+            gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
+            Some(position),
+        )
+        .map_err(|e| PlayerError::GstreamerError(format!("Failed to change rate: {}", e)))
+    }
+
+    /// Plays backwards or forwards at the current speed
+    pub fn set_reverse(&mut self, reverse: bool) -> Result<(), PlayerError> {
+        let rate = self.rate();
+        if rate.is_sign_negative() == reverse {
+            return Ok(());
+        }
+        self.set_rate(-rate)
+    }
+
+    /// Checks if playback runs backwards
+    pub fn is_reverse(&self) -> bool {
+        self.rate().is_sign_negative()
+    }
+
+    /// Gets how well the current media can be played backwards
+    pub fn reverse_support(&self) -> ReverseSupport {
+        self.reverse_support
+    }
+
+    /// Guesses how well the media can be played backwards from its seekability and demuxer
+    fn detect_reverse_support(&self) -> ReverseSupport {
+        let mut query = gst::query::Seeking::new(gst::Format::Time);
+        if self.pipeline.query(&mut query) && !query.result().0 {
+            return ReverseSupport::Unsupported;
+        }
+
+        let Some(bin) = self.pipeline.downcast_ref::<gst::Bin>() else {
+            return ReverseSupport::Unknown;
+        };
+        for element in bin.iterate_recurse().into_iter().flatten() {
+            let Some(factory) = element.factory() else {
+                continue;
+            };
+            let is_demuxer = factory
+                .metadata(gst::ELEMENT_METADATA_KLASS)
+                .is_some_and(|klass| klass.contains("Demux"));
+            if is_demuxer {
+                return if FULL_REVERSE_DEMUXERS.contains(&factory.name().as_str()) {
+                    ReverseSupport::Full
+                } else {
+                    ReverseSupport::KeyframesOnly
+                };
+            }
+        }
+        ReverseSupport::Unknown
+    }
+
+    /// Switches to the next faster rate of [`PLAYBACK_RATES`], keeping the direction
+    pub fn faster(&mut self) -> Result<(), PlayerError> {
+        let rate = self.rate();
+        match PLAYBACK_RATES
+            .iter()
+            .find(|&&r| r > rate.abs() + f64::EPSILON)
+        {
+            Some(&faster) => self.set_rate(faster.copysign(rate)),
+            None => Ok(()),
+        }
+    }
+
+    /// Switches to the next slower rate of [`PLAYBACK_RATES`], keeping the direction
+    pub fn slower(&mut self) -> Result<(), PlayerError> {
+        let rate = self.rate();
+        match PLAYBACK_RATES
+            .iter()
+            .rev()
+            .find(|&&r| r < rate.abs() - f64::EPSILON)
+        {
+            Some(&slower) => self.set_rate(slower.copysign(rate)),
+            None => Ok(()),
+        }
+    }
+
+    /// Gives access to the most recent video frame, if any has arrived yet
+    pub fn with_frame<R>(&self, f: impl FnOnce(Option<&VideoFrame>) -> R) -> R {
+        f(self.video_frame.lock().unwrap().as_ref())
+    }
+
+    /// Takes the most recent video frame if its sequence differs from `sequence`.
+    /// The pixels are moved out, the stored frame keeps everything else.
+    pub fn take_frame(&self, sequence: u64) -> Option<VideoFrame> {
+        let mut slot = self.video_frame.lock().unwrap();
+        let frame = slot.as_mut().filter(|frame| frame.sequence != sequence)?;
+        Some(VideoFrame {
+            pixels: std::mem::take(&mut frame.pixels),
+            ..*frame
+        })
+    }
+}
+
+/// Set state to NULL on drop to prevent gstreamer memory leaks
+impl Drop for MediaPlayer {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pulls the first frame of the SMPTE test pattern through an appsink configured
+    /// like the player's
+    fn pull_test_sample(width: usize, height: usize) -> gst::Sample {
+        gst::init().unwrap();
+        let pipeline = gst::parse::launch(&format!(
+            "videotestsrc num-buffers=1 pattern=smpte \
+             ! video/x-raw,format=RGBA,width={width},height={height} \
+             ! videoconvert ! appsink name=videosink caps=video/x-raw,format=RGBA"
+        ))
+        .unwrap();
+        let appsink = pipeline
+            .downcast_ref::<gst::Bin>()
+            .unwrap()
+            .by_name("videosink")
+            .unwrap()
+            .downcast::<gst_app::AppSink>()
+            .unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let sample = appsink.pull_sample().unwrap();
+        pipeline.set_state(gst::State::Null).unwrap();
+        sample
+    }
+
+    /// Stores a test pattern frame the way the player does
+    fn pull_test_frame(width: usize, height: usize) -> VideoFrame {
+        let sample = pull_test_sample(width, height);
+        let video_frame = Mutex::new(None);
+        store_sample(
+            &sample,
+            &video_frame,
+            &mut Vec::new(),
+            &AtomicU64::new(0),
+            &OnceLock::new(),
+        )
+        .unwrap();
+        video_frame.into_inner().unwrap().unwrap()
+    }
+
+    /// Checks the frame has tightly packed rows, the pattern's vertical bars stay aligned
+    fn assert_unskewed(width: usize, height: usize) {
+        let frame = pull_test_frame(width, height);
+        assert_eq!((frame.width, frame.height), (width as i32, height as i32));
+        // Four bytes per pixel and no row padding left over
+        assert_eq!(
+            std::mem::size_of_val(frame.pixels.as_slice()),
+            width * height * 4
+        );
+
+        // The top of the SMPTE pattern is made of vertical bars, so every row there
+        // must equal the first one. A wrong stride shifts each row a bit further.
+        let rows: Vec<_> = frame.pixels.chunks_exact(width).collect();
+        assert!(rows[0].iter().any(|&pixel| pixel != rows[0][0]));
+        for (y, row) in rows.iter().enumerate().take(height / 2) {
+            assert_eq!(
+                row, &rows[0],
+                "row {y} of a {width}x{height} frame is skewed"
+            );
+        }
+    }
+
+    #[test]
+    fn odd_sized_frames_are_not_skewed() {
+        assert_unskewed(321, 241);
+        assert_unskewed(641, 361);
+        assert_unskewed(99, 57);
+    }
+
+    /// Builds an RGBA sample whose plane starts after a header and whose rows are
+    /// padded, the way decoders with aligned buffers lay frames out. Every column
+    /// has its own color and the padding is filled with garbage.
+    fn padded_sample(width: usize, height: usize) -> gst::Sample {
+        gst::init().unwrap();
+        let offset = 128;
+        let stride = width * 4 + 64;
+        let mut data = vec![0xAB; offset + stride * height];
+        for y in 0..height {
+            for x in 0..width {
+                let i = offset + y * stride + x * 4;
+                data[i..i + 4].copy_from_slice(&[x as u8, (x >> 8) as u8, 0x80, 0xFF]);
+            }
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        gst_video::VideoMeta::add_full(
+            buffer.get_mut().unwrap(),
+            gst_video::VideoFrameFlags::empty(),
+            gst_video::VideoFormat::Rgba,
+            width as u32,
+            height as u32,
+            &[offset],
+            &[stride as i32],
+        )
+        .unwrap();
+        let caps = gst_video::VideoInfo::builder(
+            gst_video::VideoFormat::Rgba,
+            width as u32,
+            height as u32,
+        )
+        .build()
+        .unwrap()
+        .to_caps()
+        .unwrap();
+        gst::Sample::builder().buffer(&buffer).caps(&caps).build()
+    }
+
+    #[test]
+    fn padded_rows_are_not_skewed() {
+        for (width, height) in [(321, 241), (64, 48), (99, 57)] {
+            let video_frame = Mutex::new(None);
+            store_sample(
+                &padded_sample(width, height),
+                &video_frame,
+                &mut Vec::new(),
+                &AtomicU64::new(0),
+                &OnceLock::new(),
+            )
+            .unwrap();
+            let frame = video_frame.into_inner().unwrap().unwrap();
+            assert_eq!(frame.pixels.len(), width * height);
+
+            for (y, row) in frame.pixels.chunks_exact(width).enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    let expected =
+                        egui::Color32::from_rgba_premultiplied(x as u8, (x >> 8) as u8, 0x80, 0xFF);
+                    assert_eq!(
+                        pixel, expected,
+                        "pixel {x},{y} of a {width}x{height} frame is skewed"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn replaced_pixels_are_reused() {
+        let sample = pull_test_sample(64, 48);
+        let video_frame = Mutex::new(None);
+        let sequence = AtomicU64::new(0);
+        let mut spare = Vec::new();
+        let store = |spare: &mut Vec<egui::Color32>| {
+            store_sample(&sample, &video_frame, spare, &sequence, &OnceLock::new()).unwrap();
+        };
+
+        store(&mut spare);
+        assert!(spare.is_empty());
+        let first = video_frame
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .pixels
+            .as_ptr();
+        // The second frame takes the spare, the first frame's pixels become the spare
+        store(&mut spare);
+        assert_eq!(spare.as_ptr(), first);
+        assert_eq!(spare.len(), 64 * 48);
+        // ... and the third frame is converted into them
+        store(&mut spare);
+        let third = video_frame
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .pixels
+            .as_ptr();
+        assert_eq!(third, first);
+    }
+}
@@ -0,0 +1,467 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::location::MediaLocation;
+
+/// How long probing the duration of a single item may take
+const PROBE_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(5);
+
+/// Which items are played again once playback reaches them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepeatMode {
+    /// Stop after the last item
+    #[default]
+    Off,
+    /// Play the current item again and again
+    One,
+    /// Start over with the first item after the last one
+    All,
+}
+
+/// A single entry of a [`Playlist`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistItem {
+    pub location: MediaLocation,          // The media file or stream
+    pub title: Option<String>,            // Title shown instead of the file name
+    pub duration: Option<gst::ClockTime>, // Length of the media, once known
+}
+
+impl PlaylistItem {
+    /// Creates an item for a media file or stream, its duration is probed once it is enqueued
+    pub fn new(location: MediaLocation) -> Self {
+        PlaylistItem {
+            location,
+            title: None,
+            duration: None,
+        }
+    }
+
+    /// Gets the title of the item, falling back to its file name
+    pub fn display_name(&self) -> String {
+        match &self.title {
+            Some(title) => title.clone(),
+            None => self.location.display_name(),
+        }
+    }
+}
+
+/// An ordered list of media with shuffle and repeat.
+///
+/// The playlist only decides what plays next, the host opens the items with
+/// [`crate::MediaPlayer::load`] and queues the following one with
+/// [`crate::MediaPlayer::queue_next`] for gapless transitions.
+pub struct Playlist {
+    items: Vec<PlaylistItem>,
+    current: Option<usize>, // Index of the item that is playing
+    order: Vec<usize>,      // Item indices in play order, shuffled when shuffle is on
+    shuffle: bool,
+    repeat: RepeatMode,
+    discoverer: Option<gst_pbutils::Discoverer>, // Probes durations in the background
+    probing: usize,                              // Number of probes not answered yet
+    durations_tx: mpsc::Sender<(String, Option<gst::ClockTime>)>,
+    durations_rx: mpsc::Receiver<(String, Option<gst::ClockTime>)>,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        let (durations_tx, durations_rx) = mpsc::channel();
+        Playlist {
+            items: Vec::new(),
+            current: None,
+            order: Vec::new(),
+            shuffle: false,
+            repeat: RepeatMode::default(),
+            discoverer: None,
+            probing: 0,
+            durations_tx,
+            durations_rx,
+        }
+    }
+}
+
+impl Playlist {
+    /// Creates an empty playlist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets all items in list order
+    pub fn items(&self) -> &[PlaylistItem] {
+        &self.items
+    }
+
+    /// Gets the number of items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the playlist has no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Gets the index of the item that is playing
+    pub fn current_index(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Gets the item that is playing
+    pub fn current(&self) -> Option<&PlaylistItem> {
+        self.current.and_then(|index| self.items.get(index))
+    }
+
+    /// Adds a media file or stream to the end of the playlist and starts probing its duration
+    pub fn enqueue(&mut self, location: MediaLocation) {
+        self.push(PlaylistItem::new(location));
+    }
+
+    /// Adds an item to the end of the playlist, probing the duration of local files
+    /// if it is unknown
+    pub fn push(&mut self, item: PlaylistItem) {
+        if item.duration.is_none() && !item.location.is_network() {
+            self.probe_duration(&item.location);
+        }
+        let index = self.items.len();
+        self.items.push(item);
+
+        // A new item in a shuffled list lands anywhere after the current one
+        let start = self.position().map_or(0, |position| position + 1);
+        let position = if self.shuffle {
+            start + random_below(self.order.len() - start.min(self.order.len()) + 1)
+        } else {
+            self.order.len()
+        };
+        self.order.insert(position.min(self.order.len()), index);
+    }
+
+    /// Removes an item, returning it
+    pub fn remove(&mut self, index: usize) -> Option<PlaylistItem> {
+        if index >= self.items.len() {
+            return None;
+        }
+        let item = self.items.remove(index);
+        self.order.retain(|&i| i != index);
+        for i in &mut self.order {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        self.current = match self.current {
+            Some(current) if current == index => None,
+            Some(current) if current > index => Some(current - 1),
+            current => current,
+        };
+        Some(item)
+    }
+
+    /// Removes all items
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.order.clear();
+        self.current = None;
+    }
+
+    /// Moves an item to another place in the list, keeping the play order of shuffled items
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        if from >= self.items.len() || from == to {
+            return;
+        }
+        let to = to.min(self.items.len() - 1);
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+
+        let moved = |index: usize| {
+            if index == from {
+                to
+            } else if from < to && (from + 1..=to).contains(&index) {
+                index - 1
+            } else if to < from && (to..from).contains(&index) {
+                index + 1
+            } else {
+                index
+            }
+        };
+        if self.shuffle {
+            for index in &mut self.order {
+                *index = moved(*index);
+            }
+        } else {
+            self.order = (0..self.items.len()).collect();
+        }
+        self.current = self.current.map(moved);
+    }
+
+    /// Makes an item the current one and returns its location
+    pub fn select(&mut self, index: usize) -> Option<&MediaLocation> {
+        let item = self.items.get(index)?;
+        self.current = Some(index);
+        Some(&item.location)
+    }
+
+    /// Makes the item with the given URI current and returns its location. The item
+    /// that plays next wins when the URI appears more than once.
+    pub fn select_uri(&mut self, uri: &str) -> Option<&MediaLocation> {
+        let index = self
+            .upcoming()
+            .filter(|&index| self.items[index].location.uri() == uri)
+            .or_else(|| {
+                self.items
+                    .iter()
+                    .position(|item| item.location.uri() == uri)
+            })?;
+        self.select(index)
+    }
+
+    /// Gets the item played automatically after the current one, honouring repeat
+    pub fn peek_next(&self) -> Option<&MediaLocation> {
+        let index = self.upcoming()?;
+        Some(&self.items[index].location)
+    }
+
+    /// Moves on to the item returned by [`Playlist::peek_next`], after the player
+    /// switched to it on its own
+    pub fn advance(&mut self) {
+        self.current = self.upcoming();
+    }
+
+    /// Skips to the next item in play order and returns its location
+    pub fn next_item(&mut self) -> Option<&MediaLocation> {
+        let index = self.following()?;
+        self.select(index)
+    }
+
+    /// Goes back to the previous item in play order and returns its location
+    pub fn previous_item(&mut self) -> Option<&MediaLocation> {
+        let index = match self.position() {
+            Some(0) if self.repeat == RepeatMode::All => self.order.last().copied(),
+            Some(0) => None,
+            Some(position) => Some(self.order[position - 1]),
+            None => self.order.first().copied(),
+        }?;
+        self.select(index)
+    }
+
+    /// Gets the item that plays automatically after the current one
+    fn upcoming(&self) -> Option<usize> {
+        match self.repeat {
+            RepeatMode::One => self.current.or_else(|| self.order.first().copied()),
+            RepeatMode::Off | RepeatMode::All => self.following(),
+        }
+    }
+
+    /// Gets the item after the current one in play order, wrapping around with repeat-all
+    fn following(&self) -> Option<usize> {
+        match self.position() {
+            Some(position) if position + 1 < self.order.len() => Some(self.order[position + 1]),
+            Some(_) if self.repeat == RepeatMode::All => self.order.first().copied(),
+            Some(_) => None,
+            None => self.order.first().copied(),
+        }
+    }
+
+    /// Gets where the current item is in play order
+    fn position(&self) -> Option<usize> {
+        let current = self.current?;
+        self.order.iter().position(|&index| index == current)
+    }
+
+    /// Turns shuffle on or off. Shuffling keeps the current item and plays the others
+    /// in random order after it.
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+        self.order = (0..self.items.len()).collect();
+        if !shuffle {
+            return;
+        }
+
+        // Fisher-Yates shuffle
+        for i in (1..self.order.len()).rev() {
+            self.order.swap(i, random_below(i + 1));
+        }
+        if let Some(current) = self.current {
+            self.order.retain(|&index| index != current);
+            self.order.insert(0, current);
+        }
+    }
+
+    /// Checks if items are played in random order
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle
+    }
+
+    /// Sets which items are played again
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    /// Gets which items are played again
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    /// Sets the duration of every item playing the given file, e.g. once the player knows it
+    pub fn set_duration(&mut self, location: &MediaLocation, duration: gst::ClockTime) {
+        for item in self
+            .items
+            .iter_mut()
+            .filter(|item| item.location == *location)
+        {
+            item.duration = Some(duration);
+        }
+    }
+
+    /// Starts probing the duration of a file without playing it
+    fn probe_duration(&mut self, location: &MediaLocation) {
+        if self.discoverer.is_none() {
+            let Ok(discoverer) = gst_pbutils::Discoverer::new(PROBE_TIMEOUT) else {
+                return;
+            };
+            let durations_tx = self.durations_tx.clone();
+            discoverer.connect_discovered(move |_, info, _| {
+                let _ = durations_tx.send((info.uri().to_string(), info.duration()));
+            });
+            discoverer.start();
+            self.discoverer = Some(discoverer);
+        }
+        if let Some(discoverer) = &self.discoverer {
+            if discoverer.discover_uri_async(location.uri()).is_ok() {
+                self.probing += 1;
+            }
+        }
+    }
+
+    /// Takes the durations probed since the last call. The probes report back through
+    /// the GLib main context, which [`crate::MediaPlayer::update`] iterates.
+    pub fn update(&mut self) {
+        while let Ok((uri, duration)) = self.durations_rx.try_recv() {
+            self.probing = self.probing.saturating_sub(1);
+            let Some(duration) = duration else {
+                continue;
+            };
+            for item in &mut self.items {
+                if item.duration.is_none() && item.location.uri() == uri {
+                    item.duration = Some(duration);
+                }
+            }
+        }
+    }
+
+    /// Draws the items with their durations, with shuffle and repeat toggles above them.
+    /// Items can be reordered by dragging them. Returns the index of the item the user
+    /// double-clicked to play, if any.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<usize> {
+        self.update();
+        if self.probing > 0 {
+            // Nothing else repaints while paused, so check back for probed durations
+            ui.ctx().request_repaint_after(Duration::from_millis(250));
+        }
+
+        ui.horizontal(|ui| {
+            let shuffle = self.shuffle;
+            if ui
+                .selectable_label(shuffle, "🔀")
+                .on_hover_text("Shuffle")
+                .clicked()
+            {
+                self.set_shuffle(!shuffle);
+            }
+            let (label, hint, next) = match self.repeat {
+                RepeatMode::Off => ("🔁", "Repeat off", RepeatMode::All),
+                RepeatMode::All => ("🔁", "Repeat all", RepeatMode::One),
+                RepeatMode::One => ("🔂", "Repeat one", RepeatMode::Off),
+            };
+            if ui
+                .selectable_label(self.repeat != RepeatMode::Off, label)
+                .on_hover_text(hint)
+                .clicked()
+            {
+                self.repeat = next;
+            }
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+        ui.separator();
+
+        let mut play = None;
+        let mut remove = None;
+        let mut reorder = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, item) in self.items.iter().enumerate() {
+                let response = ui
+                    .dnd_drag_source(egui::Id::new(("playlist_item", index)), index, |ui| {
+                        ui.horizontal(|ui| {
+                            let name = ui
+                                .selectable_label(self.current == Some(index), item.display_name());
+                            if name.double_clicked() {
+                                play = Some(index);
+                            }
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                                        remove = Some(index);
+                                    }
+                                    if let Some(duration) = item.duration {
+                                        ui.weak(format!(
+                                            "{:02}:{:02}",
+                                            duration.seconds() / 60,
+                                            duration.seconds() % 60
+                                        ));
+                                    }
+                                },
+                            );
+                        });
+                    })
+                    .response;
+
+                // Show where a dragged item would land, above or below this one
+                if let (Some(pointer), Some(dragged)) = (
+                    ui.input(|i| i.pointer.interact_pos()),
+                    response.dnd_hover_payload::<usize>(),
+                ) {
+                    let rect = response.rect;
+                    let (y, target) = if pointer.y < rect.center().y {
+                        (rect.top(), index)
+                    } else {
+                        (rect.bottom(), index + 1)
+                    };
+                    ui.painter().hline(
+                        rect.x_range(),
+                        y,
+                        egui::Stroke::new(2.0, ui.visuals().selection.bg_fill),
+                    );
+                    if response.dnd_release_payload::<usize>().is_some() {
+                        // Inserting after the item's old place shifts the target up by one
+                        let target = if *dragged < target {
+                            target - 1
+                        } else {
+                            target
+                        };
+                        reorder = Some((*dragged, target));
+                    }
+                }
+            }
+        });
+
+        if let Some((from, to)) = reorder {
+            self.move_item(from, to);
+        }
+        if let Some(index) = remove {
+            self.remove(index);
+        }
+        play
+    }
+}
+
+/// Gets a random number below `bound`, good enough for shuffling
+fn random_below(bound: usize) -> usize {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(bound);
+    (hasher.finish() % bound.max(1) as u64) as usize
+}
@@ -0,0 +1,512 @@
+use gstreamer as gst;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::error::{ErrorDetails, PlayerError};
+use crate::location::MediaLocation;
+use crate::playlist::PlaylistItem;
+
+/// A playlist file format that can be imported and exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    M3u,  // Extended M3U, also used for .m3u8
+    Pls,  // Winamp/Shoutcast PLS
+    Xspf, // XML Shareable Playlist Format
+}
+
+impl PlaylistFormat {
+    /// File extensions of all supported playlist formats, for file dialog filters
+    pub const EXTENSIONS: [&'static str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+    /// Guesses the format of a playlist file from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None,
+        }
+    }
+
+    /// Reads the items of a playlist file. Relative entries are resolved against the
+    /// directory of the playlist, entries that cannot be played from are skipped.
+    pub fn read(path: &Path) -> Result<Vec<PlaylistItem>, PlayerError> {
+        let format = Self::from_path(path)
+            .ok_or_else(|| playlist_error(path, "Not an M3U, PLS or XSPF playlist".to_string()))?;
+        let bytes = std::fs::read(path).map_err(|e| playlist_error(path, e.to_string()))?;
+        let text = String::from_utf8_lossy(&bytes);
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Ok(format.parse(&text, base_dir))
+    }
+
+    /// Writes items to a playlist file in the format matching its extension
+    pub fn write(path: &Path, items: &[PlaylistItem]) -> Result<(), PlayerError> {
+        let format = Self::from_path(path)
+            .ok_or_else(|| playlist_error(path, "Not an M3U, PLS or XSPF playlist".to_string()))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        std::fs::write(path, format.format(items, base_dir))
+            .map_err(|e| playlist_error(path, e.to_string()))
+    }
+
+    /// Parses the text of a playlist, resolving relative entries against `base_dir`
+    pub fn parse(self, text: &str, base_dir: &Path) -> Vec<PlaylistItem> {
+        match self {
+            PlaylistFormat::M3u => parse_m3u(text, base_dir),
+            PlaylistFormat::Pls => parse_pls(text, base_dir),
+            PlaylistFormat::Xspf => parse_xspf(text, base_dir),
+        }
+    }
+
+    /// Formats items as playlist text. Files inside `base_dir` are written relative to it,
+    /// except in XSPF which uses absolute URIs.
+    pub fn format(self, items: &[PlaylistItem], base_dir: &Path) -> String {
+        match self {
+            PlaylistFormat::M3u => format_m3u(items, base_dir),
+            PlaylistFormat::Pls => format_pls(items, base_dir),
+            PlaylistFormat::Xspf => format_xspf(items),
+        }
+    }
+}
+
+/// Creates an error for a playlist file that could not be read or written
+fn playlist_error(path: &Path, message: String) -> PlayerError {
+    PlayerError::PlaylistFile(ErrorDetails {
+        message,
+        source: Some(path.display().to_string()),
+        debug: None,
+    })
+}
+
+/// Turns a playlist entry into a media location. Entries may be URIs, absolute paths
+/// or paths relative to the playlist.
+fn resolve_entry(entry: &str, base_dir: &Path) -> Option<MediaLocation> {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return None;
+    }
+    let location = if entry.contains("://") {
+        MediaLocation::from_uri(entry)
+    } else {
+        MediaLocation::from_path(base_dir.join(entry))
+    };
+    location.ok()
+}
+
+/// Gets the entry written for a location, relative to the playlist when it is a file
+/// inside its directory. Paths that are not UTF-8 are written as their `file://` URI,
+/// as playlists are text.
+fn relative_entry(location: &MediaLocation, base_dir: &Path) -> String {
+    let entry = location
+        .path()
+        .and_then(|path| path.strip_prefix(base_dir).unwrap_or(path).to_str());
+    entry.unwrap_or(location.uri()).to_string()
+}
+
+/// Parses an M3U playlist, reading titles and durations from `#EXTINF` lines
+fn parse_m3u(text: &str, base_dir: &Path) -> Vec<PlaylistItem> {
+    let mut items = Vec::new();
+    let mut info = None;
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<seconds>[ attributes],<title>
+            let (length, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let seconds = length
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse::<f64>().ok())
+                .map(|s| s as i64);
+            info = Some((seconds, title.trim().to_string()));
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else if let Some(location) = resolve_entry(line, base_dir) {
+            let mut item = PlaylistItem::new(location);
+            if let Some((seconds, title)) = info.take() {
+                item.duration = seconds_to_time(seconds);
+                item.title = Some(title).filter(|title| !title.is_empty());
+            }
+            items.push(item);
+        } else {
+            info = None;
+        }
+    }
+    items
+}
+
+/// Formats items as an extended M3U playlist
+fn format_m3u(items: &[PlaylistItem], base_dir: &Path) -> String {
+    let mut text = String::from("#EXTM3U\n");
+    for item in items {
+        let seconds = item
+            .duration
+            .map_or(-1, |duration| duration.seconds() as i64);
+        let _ = writeln!(text, "#EXTINF:{},{}", seconds, item.display_name());
+        let _ = writeln!(text, "{}", relative_entry(&item.location, base_dir));
+    }
+    text
+}
+
+/// Parses a PLS playlist. Entries are numbered `File1`, `Title1`, `Length1`, ...
+fn parse_pls(text: &str, base_dir: &Path) -> Vec<PlaylistItem> {
+    let mut entries: Vec<(u32, PlaylistItem)> = Vec::new();
+    let mut titles = Vec::new();
+    let mut lengths = Vec::new();
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let number = |prefix: &str| key.strip_prefix(prefix)?.parse::<u32>().ok();
+        if let Some(number) = number("file") {
+            if let Some(location) = resolve_entry(value, base_dir) {
+                entries.push((number, PlaylistItem::new(location)));
+            }
+        } else if let Some(number) = number("title") {
+            titles.push((number, value.trim().to_string()));
+        } else if let Some(number) = number("length") {
+            lengths.push((number, value.trim().parse().ok()));
+        }
+    }
+
+    entries.sort_by_key(|(number, _)| *number);
+    for (number, item) in &mut entries {
+        if let Some((_, title)) = titles.iter().find(|(n, _)| n == number) {
+            item.title = Some(title.clone()).filter(|title| !title.is_empty());
+        }
+        if let Some((_, seconds)) = lengths.iter().find(|(n, _)| n == number) {
+            item.duration = seconds_to_time(*seconds);
+        }
+    }
+    entries.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Formats items as a version 2 PLS playlist
+fn format_pls(items: &[PlaylistItem], base_dir: &Path) -> String {
+    let mut text = String::from("[playlist]\n");
+    for (index, item) in items.iter().enumerate() {
+        let number = index + 1;
+        let seconds = item
+            .duration
+            .map_or(-1, |duration| duration.seconds() as i64);
+        let _ = writeln!(
+            text,
+            "File{}={}",
+            number,
+            relative_entry(&item.location, base_dir)
+        );
+        let _ = writeln!(text, "Title{}={}", number, item.display_name());
+        let _ = writeln!(text, "Length{}={}", number, seconds);
+    }
+    let _ = writeln!(text, "NumberOfEntries={}", items.len());
+    text.push_str("Version=2\n");
+    text
+}
+
+/// Parses the tracks of an XSPF playlist. Only `location`, `title` and `duration`
+/// (in milliseconds) are read.
+fn parse_xspf(text: &str, base_dir: &Path) -> Vec<PlaylistItem> {
+    let mut items = Vec::new();
+    let mut rest = text;
+    while let Some((content_start, empty)) = find_start_tag(rest, "track") {
+        rest = &rest[content_start..];
+        let end = if empty {
+            0
+        } else {
+            rest.find("</track>").unwrap_or(rest.len())
+        };
+        let track = &rest[..end];
+        rest = &rest[end..];
+
+        let Some(location) = xml_element(track, "location")
+            .and_then(|location| resolve_xspf_location(&location, base_dir))
+        else {
+            continue;
+        };
+        let mut item = PlaylistItem::new(location);
+        item.title = xml_element(track, "title").filter(|title| !title.is_empty());
+        item.duration = xml_element(track, "duration")
+            .and_then(|ms| ms.trim().parse().ok())
+            .map(gst::ClockTime::from_mseconds);
+        items.push(item);
+    }
+    items
+}
+
+/// Formats items as an XSPF playlist with absolute URIs
+fn format_xspf(items: &[PlaylistItem]) -> String {
+    let mut text = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n",
+    );
+    for item in items {
+        text.push_str("    <track>\n");
+        let _ = writeln!(
+            text,
+            "      <location>{}</location>",
+            xml_escape(item.location.uri())
+        );
+        if let Some(title) = &item.title {
+            let _ = writeln!(text, "      <title>{}</title>", xml_escape(title));
+        }
+        if let Some(duration) = item.duration {
+            let _ = writeln!(text, "      <duration>{}</duration>", duration.mseconds());
+        }
+        text.push_str("    </track>\n");
+    }
+    text.push_str("  </trackList>\n</playlist>\n");
+    text
+}
+
+/// Resolves an XSPF location. Locations are URIs, so relative ones are percent-encoded
+/// paths that are decoded before they are joined with the playlist directory.
+fn resolve_xspf_location(location: &str, base_dir: &Path) -> Option<MediaLocation> {
+    let location = location.trim();
+    if location.contains("://") {
+        return resolve_entry(location, base_dir);
+    }
+    match glib::Uri::unescape_string(location, None) {
+        Some(decoded) => resolve_entry(&decoded, base_dir),
+        // Not a valid escape, take the text as a plain path
+        None => resolve_entry(location, base_dir),
+    }
+}
+
+/// Finds the first `<name>` start tag in `xml`, with or without attributes, and gets
+/// the index after it and whether it is an empty `<name/>` element
+fn find_start_tag(xml: &str, name: &str) -> Option<(usize, bool)> {
+    let open = format!("<{}", name);
+    let mut offset = 0;
+    while let Some(found) = xml[offset..].find(&open) {
+        let after_name = offset + found + open.len();
+        // Skip longer names that start the same, such as <trackList> for <track>
+        let boundary = xml[after_name..].chars().next()?;
+        if boundary == '>' || boundary == '/' || boundary.is_whitespace() {
+            let end = after_name + xml[after_name..].find('>')?;
+            return Some((end + 1, xml[..end].ends_with('/')));
+        }
+        offset = after_name;
+    }
+    None
+}
+
+/// Gets the unescaped text of the first `<name>` element in `xml`
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    let close = format!("</{}>", name);
+    let (start, empty) = find_start_tag(xml, name)?;
+    if empty {
+        return Some(String::new());
+    }
+    let end = start + xml[start..].find(&close)?;
+    let text = xml[start..end].trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+        .map_or_else(|| xml_unescape(text), str::to_string);
+    Some(text)
+}
+
+/// Escapes text for use in XML element content
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Replaces the predefined XML entities and numeric character references
+fn xml_unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Converts a playlist length in seconds, where negative means unknown
+fn seconds_to_time(seconds: Option<i64>) -> Option<gst::ClockTime> {
+    seconds
+        .filter(|&seconds| seconds >= 0)
+        .map(|seconds| gst::ClockTime::from_seconds(seconds as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// An absolute directory the playlists are read from
+    fn base_dir() -> PathBuf {
+        std::env::temp_dir().join("playlists")
+    }
+
+    fn file_item(name: &str, title: Option<&str>, seconds: Option<u64>) -> PlaylistItem {
+        PlaylistItem {
+            location: MediaLocation::from_path(base_dir().join(name)).unwrap(),
+            title: title.map(str::to_string),
+            duration: seconds.map(gst::ClockTime::from_seconds),
+        }
+    }
+
+    fn uri_item(uri: &str, title: Option<&str>) -> PlaylistItem {
+        PlaylistItem {
+            location: MediaLocation::from_uri(uri).unwrap(),
+            title: title.map(str::to_string),
+            duration: None,
+        }
+    }
+
+    #[test]
+    fn m3u_reads_titles_and_durations() {
+        let text = "\u{feff}#EXTM3U\n\
+                    #EXTINF:123,Artist - Song\n\
+                    song.mp3\n\
+                    \n\
+                    # A comment\n\
+                    #EXTINF:-1 tvg-id=\"radio\",Radio\n\
+                    http://radio.example.com/stream\n\
+                    sub dir/clip.mkv\n";
+        assert_eq!(
+            PlaylistFormat::M3u.parse(text, &base_dir()),
+            [
+                file_item("song.mp3", Some("Artist - Song"), Some(123)),
+                uri_item("http://radio.example.com/stream", Some("Radio")),
+                file_item("sub dir/clip.mkv", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_matches_entries_by_number() {
+        let text = "[playlist]\n\
+                    Title2=Second\n\
+                    File2=b.ogg\n\
+                    Length1=60\n\
+                    File1=a.ogg\n\
+                    Title1=First\n\
+                    Length2=-1\n\
+                    file3=http://example.com/c.ogg\n\
+                    NumberOfEntries=3\n\
+                    Version=2\n";
+        assert_eq!(
+            PlaylistFormat::Pls.parse(text, &base_dir()),
+            [
+                file_item("a.ogg", Some("First"), Some(60)),
+                file_item("b.ogg", Some("Second"), None),
+                uri_item("http://example.com/c.ogg", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_playlist() {
+        let text = "../music/a.mp3\nb.mp3\n";
+        assert_eq!(
+            PlaylistFormat::M3u.parse(text, &base_dir()),
+            [
+                file_item("../music/a.mp3", None, None),
+                file_item("b.mp3", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn xspf_reads_tracks() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Mix</title>
+  <trackList>
+    <track id="1">
+      <location>My%20Song%20%231.mp3</location>
+      <title><![CDATA[Rock & <Roll>]]></title>
+      <duration>61000</duration>
+    </track>
+    <track>
+      <location>http://example.com/a.ogg?x=1&amp;y=2</location>
+      <title>Tom &amp; Jerry &#233;&#x21;</title>
+    </track>
+    <track/>
+  </trackList>
+</playlist>
+"#;
+        assert_eq!(
+            PlaylistFormat::Xspf.parse(text, &base_dir()),
+            [
+                file_item("My Song #1.mp3", Some("Rock & <Roll>"), Some(61)),
+                uri_item("http://example.com/a.ogg?x=1&y=2", Some("Tom & Jerry é!")),
+            ]
+        );
+    }
+
+    #[test]
+    fn xspf_round_trip() {
+        let items = [
+            file_item("100% & more.mp3", Some("<Live>"), Some(75)),
+            uri_item("rtsp://camera.local/stream", Some("Camera")),
+        ];
+        let text = PlaylistFormat::Xspf.format(&items, &base_dir());
+        assert_eq!(PlaylistFormat::Xspf.parse(&text, &base_dir()), items);
+    }
+
+    #[test]
+    fn m3u_and_pls_round_trip() {
+        let items = [
+            file_item("first song.mp3", Some("First"), Some(90)),
+            file_item("sub/second #2.ogg", Some("Second"), None),
+            uri_item("https://example.com/live.m3u8", Some("Live")),
+        ];
+        for format in [PlaylistFormat::M3u, PlaylistFormat::Pls] {
+            let text = format.format(&items, &base_dir());
+            // Files inside the playlist directory are written relative to it
+            assert!(text.contains("\nfirst song.mp3\n") || text.contains("=first song.mp3\n"));
+            assert_eq!(format.parse(&text, &base_dir()), items, "{format:?}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_that_are_not_utf8_are_written_as_uris() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = base_dir().join(OsStr::from_bytes(b"caf\xe9.mp3"));
+        let items = [PlaylistItem::new(MediaLocation::from_path(&path).unwrap())];
+        for format in [PlaylistFormat::M3u, PlaylistFormat::Pls] {
+            let text = format.format(&items, &base_dir());
+            assert!(text.contains("caf%E9.mp3"), "{text}");
+            let parsed = format.parse(&text, &base_dir());
+            assert_eq!(parsed[0].location.path(), Some(path.as_path()));
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+/// How often and how quickly a network stream that failed is opened again.
+///
+/// The wait before each attempt grows by `multiplier`, starting at `initial_delay`
+/// and never exceeding `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,       // Attempts before giving up, 0 never retries
+    pub initial_delay: Duration, // Wait before the first attempt
+    pub max_delay: Duration,     // Longest wait between two attempts
+    pub multiplier: f64,         // Growth of the wait after every failed attempt
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy that never opens a failed stream again
+    pub fn never() -> Self {
+        RetryPolicy {
+            max_attempts: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the wait before an attempt, counted from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let seconds = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        Duration::try_from_secs_f64(seconds)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_by_the_multiplier_up_to_the_maximum() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<_> = (1..=7)
+            .map(|attempt| policy.delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_millis(1000),
+            multiplier: 1.5,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(250));
+        assert_eq!(policy.delay(3), Duration::from_micros(562_500));
+        assert_eq!(policy.delay(10), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn delay_never_shrinks() {
+        for multiplier in [1.0, 0.5, 0.0, -2.0] {
+            let policy = RetryPolicy {
+                multiplier,
+                ..RetryPolicy::default()
+            };
+            for attempt in [1, 2, 10] {
+                assert_eq!(
+                    policy.delay(attempt),
+                    Duration::from_secs(1),
+                    "{multiplier}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_capped_for_huge_attempts() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1_000), Duration::from_secs(30));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(30));
+
+        // An initial delay above the maximum is capped as well
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(60),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(30));
+    }
+}
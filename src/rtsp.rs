@@ -0,0 +1,83 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::fmt;
+
+/// Lower transport used to receive RTSP streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RtspTransport {
+    /// Try UDP first and fall back to TCP, as rtspsrc does by default
+    #[default]
+    Auto,
+    /// Interleave the media in the RTSP TCP connection, works through firewalls and NAT
+    Tcp,
+    /// Receive the media as UDP unicast, lowest latency but may be blocked
+    Udp,
+}
+
+impl RtspTransport {
+    /// Gets the value of the rtspsrc `protocols` property, `None` for its default
+    fn protocols(self) -> Option<&'static str> {
+        match self {
+            RtspTransport::Auto => None,
+            RtspTransport::Tcp => Some("tcp"),
+            RtspTransport::Udp => Some("udp"),
+        }
+    }
+}
+
+/// How `rtsp://` media is received, applied when the media is opened
+#[derive(Clone, PartialEq, Eq)]
+pub struct RtspSettings {
+    pub transport: RtspTransport, // Lower transport to use
+    pub latency: gst::ClockTime,  // Size of the jitter buffer
+    pub user_id: Option<String>,  // User name, if not given in the URI
+    pub user_pw: Option<String>,  // Password, if not given in the URI
+}
+
+impl Default for RtspSettings {
+    fn default() -> Self {
+        RtspSettings {
+            transport: RtspTransport::Auto,
+            latency: gst::ClockTime::from_mseconds(2000),
+            user_id: None,
+            user_pw: None,
+        }
+    }
+}
+
+/// Keeps the password out of logs and error reports
+impl fmt::Debug for RtspSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RtspSettings")
+            .field("transport", &self.transport)
+            .field("latency", &self.latency)
+            .field("user_id", &self.user_id)
+            .field("user_pw", &self.user_pw.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl RtspSettings {
+    /// Configures the source playbin created for the media, if it is an RTSP source
+    pub(crate) fn apply(&self, source: &gst::Element) {
+        let is_rtspsrc = source
+            .factory()
+            .is_some_and(|factory| factory.name() == "rtspsrc");
+        if !is_rtspsrc {
+            return;
+        }
+        if let Some(protocols) = self.transport.protocols() {
+            source.set_property_from_str("protocols", protocols);
+        }
+        source.set_property(
+            "latency",
+            u32::try_from(self.latency.mseconds()).unwrap_or(u32::MAX),
+        );
+        if let Some(user_id) = &self.user_id {
+            source.set_property("user-id", user_id);
+        }
+        if let Some(user_pw) = &self.user_pw {
+            source.set_property("user-pw", user_pw);
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::PlayerError;
+use crate::location::is_network_uri;
+use crate::rtsp::RtspSettings;
+
+/// Most recorded data queued in the playback pipeline ahead of what it plays
+const MAX_QUEUED_BYTES: u64 = 2 * 1024 * 1024;
+
+/// How long recording may take to reach the first keyframe before it is given up
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A recording kept behind live streams, so they can be paused, rewound and caught up again.
+///
+/// Live streams are recorded as MPEG-TS into a ring buffer in memory and played back from
+/// it. The seekable window is the part of the stream the buffer holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeShift {
+    pub max_size: u64, // Size of the ring buffer in bytes, the oldest data is dropped beyond it
+}
+
+impl Default for TimeShift {
+    fn default() -> Self {
+        TimeShift {
+            max_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// A piece of the recorded stream
+struct Chunk {
+    time: gst::ClockTime, // Recording time the chunk arrived at
+    keyframe: bool,       // Playback can start at this chunk
+    buffer: gst::Buffer,  // MPEG-TS packets
+}
+
+/// The recorded chunks and where playback reads them
+#[derive(Default)]
+struct RingState {
+    chunks: VecDeque<Chunk>,
+    size: u64,                       // Bytes held by the chunks
+    max_size: u64,                   // Bytes kept before the oldest chunks are dropped
+    first_index: u64,                // Index of the oldest chunk, counted since recording started
+    cursor: u64,                     // Index of the next chunk handed to playback
+    source: Option<gst_app::AppSrc>, // Source of the playback pipeline, if attached
+    ended: bool,                     // Recording finished, playback ends after the last chunk
+}
+
+impl RingState {
+    /// Adds a chunk, dropping the oldest ones beyond the maximum size
+    fn push(&mut self, chunk: Chunk) {
+        self.size += chunk.buffer.size() as u64;
+        self.chunks.push_back(chunk);
+        while self.size > self.max_size && self.chunks.len() > 1 {
+            if let Some(oldest) = self.chunks.pop_front() {
+                self.size -= oldest.buffer.size() as u64;
+                self.first_index += 1;
+            }
+        }
+    }
+
+    /// Gets the chunk with an index counted since recording started, if it is still kept
+    fn chunk(&self, index: u64) -> Option<&Chunk> {
+        let offset = index.checked_sub(self.first_index)?;
+        self.chunks.get(usize::try_from(offset).ok()?)
+    }
+
+    /// Hands chunks to playback for as long as it wants more
+    fn feed(&mut self, wants_data: &AtomicBool) {
+        let Some(source) = self.source.clone() else {
+            return;
+        };
+        if self.cursor < self.first_index {
+            // Playback fell behind the oldest chunk kept, continue at the oldest keyframe
+            match self.chunks.iter().position(|chunk| chunk.keyframe) {
+                Some(offset) => self.cursor = self.first_index + offset as u64,
+                None => return,
+            }
+        }
+        while wants_data.load(Ordering::Acquire) {
+            let Some(chunk) = self.chunk(self.cursor) else {
+                if self.ended {
+                    let _ = source.end_of_stream();
+                    self.source = None;
+                }
+                return;
+            };
+            // Playback times the stream by the clock references in the packets
+            let mut buffer = chunk.buffer.clone();
+            let buffer_mut = buffer.make_mut();
+            buffer_mut.set_pts(gst::ClockTime::NONE);
+            buffer_mut.set_dts(gst::ClockTime::NONE);
+            self.cursor += 1;
+            if source.push_buffer(buffer).is_err() {
+                // The source is shutting down
+                self.source = None;
+                return;
+            }
+        }
+    }
+}
+
+struct RingInner {
+    state: Mutex<RingState>,
+    wants_data: AtomicBool, // Playback asked for data, kept outside the lock for enough-data
+}
+
+impl RingInner {
+    fn feed(&self) {
+        self.state.lock().unwrap().feed(&self.wants_data);
+    }
+}
+
+/// A recording shared by the recorder filling it and the playback pipeline reading it
+#[derive(Clone)]
+pub(crate) struct Ring(Arc<RingInner>);
+
+impl Ring {
+    fn new(max_size: u64) -> Self {
+        Ring(Arc::new(RingInner {
+            state: Mutex::new(RingState {
+                max_size,
+                ..Default::default()
+            }),
+            wants_data: AtomicBool::new(false),
+        }))
+    }
+
+    /// Adds a recorded chunk and passes it on if playback waits for it
+    fn push(&self, chunk: Chunk) {
+        let mut state = self.0.state.lock().unwrap();
+        state.push(chunk);
+        state.feed(&self.0.wants_data);
+    }
+
+    /// Marks the recording as finished, so playback ends once it has played everything
+    pub(crate) fn end(&self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.ended = true;
+        state.feed(&self.0.wants_data);
+    }
+
+    /// Gets the recording times playback can start from, from the oldest keyframe kept
+    /// to the newest chunk
+    pub(crate) fn window(&self) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        let state = self.0.state.lock().unwrap();
+        let start = state.chunks.iter().find(|chunk| chunk.keyframe)?.time;
+        let end = state.chunks.back()?.time;
+        Some((start, end))
+    }
+
+    /// Moves playback to the last keyframe at or before `time`, or the oldest one, and
+    /// detaches the current playback pipeline. Gets the recording time playback
+    /// continues at.
+    pub(crate) fn rewind_to(&self, time: gst::ClockTime) -> Option<gst::ClockTime> {
+        let mut state = self.0.state.lock().unwrap();
+        state.source = None;
+        self.0.wants_data.store(false, Ordering::Release);
+        let offset = state
+            .chunks
+            .iter()
+            .rposition(|chunk| chunk.keyframe && chunk.time <= time)
+            .or_else(|| state.chunks.iter().position(|chunk| chunk.keyframe))?;
+        state.cursor = state.first_index + offset as u64;
+        Some(state.chunks[offset].time)
+    }
+
+    /// Makes the `appsrc` of the playback pipeline play the recording from the cursor
+    pub(crate) fn attach(&self, source: &gst_app::AppSrc) {
+        source.set_caps(Some(
+            &gst::Caps::builder("video/mpegts")
+                .field("systemstream", true)
+                .field("packetsize", 188i32)
+                .build(),
+        ));
+        source.set_format(gst::Format::Bytes);
+        source.set_stream_type(gst_app::AppStreamType::Stream);
+        source.set_max_bytes(MAX_QUEUED_BYTES);
+        self.0.state.lock().unwrap().source = Some(source.clone());
+
+        let need_data = Arc::downgrade(&self.0);
+        let enough_data = Arc::downgrade(&self.0);
+        source.set_callbacks(
+            gst_app::AppSrcCallbacks::builder()
+                .need_data(move |_, _| {
+                    if let Some(inner) = need_data.upgrade() {
+                        inner.wants_data.store(true, Ordering::Release);
+                        inner.feed();
+                    }
+                })
+                // Emitted from within push_buffer while the ring is locked,
+                // so only the flag may be touched here
+                .enough_data(move |_| {
+                    if let Some(inner) = enough_data.upgrade() {
+                        inner.wants_data.store(false, Ordering::Release);
+                    }
+                })
+                .build(),
+        );
+    }
+}
+
+/// Records a live stream into a ring, remuxed to MPEG-TS without decoding it
+pub(crate) struct Recorder {
+    pipeline: gst::Pipeline, // Source, parsers, muxer and the sink filling the ring
+    ring: Ring,              // The recording
+    network: bool,           // The stream comes from a network source
+    started: Instant,        // When recording started
+}
+
+impl Recorder {
+    /// Starts recording the stream at `uri`, configuring RTSP sources with `rtsp`
+    pub(crate) fn start(
+        uri: &str,
+        time_shift: &TimeShift,
+        rtsp: RtspSettings,
+    ) -> Result<Self, PlayerError> {
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory).build().map_err(|e| {
+                PlayerError::GstreamerError(format!("Failed to create {}: {}", factory, e))
+            })
+        };
+        let pipeline = gst::Pipeline::new();
+        let source = make("urisourcebin")?;
+        source.set_property("uri", uri);
+        let mux = make("mpegtsmux")?;
+        let appsink = gst_app::AppSink::builder().sync(false).build();
+        pipeline
+            .add_many([&source, &mux, appsink.upcast_ref()])
+            .map_err(|e| PlayerError::GstreamerError(e.to_string()))?;
+        mux.link(&appsink)
+            .map_err(|e| PlayerError::GstreamerError(e.to_string()))?;
+
+        source.connect("source-setup", false, move |values| {
+            let source = values[1].get::<gst::Element>().unwrap();
+            rtsp.apply(&source);
+            None
+        });
+
+        // Every stream of the source is parsed, and muxed when MPEG-TS can carry it.
+        // A pad that fails to link stops the recording with a not-linked error.
+        let pipeline_weak = pipeline.downgrade();
+        let mux_weak = mux.downgrade();
+        source.connect_pad_added(move |_, pad| {
+            let (Some(pipeline), Some(mux)) = (pipeline_weak.upgrade(), mux_weak.upgrade()) else {
+                return;
+            };
+            let Ok(parsebin) = gst::ElementFactory::make("parsebin").build() else {
+                return;
+            };
+            let parsed_pipeline = pipeline.downgrade();
+            let parsed_mux = mux.downgrade();
+            parsebin.connect_pad_added(move |_, pad| {
+                if let (Some(pipeline), Some(mux)) =
+                    (parsed_pipeline.upgrade(), parsed_mux.upgrade())
+                {
+                    link_stream(&pipeline, &mux, pad);
+                }
+            });
+            if pipeline.add(&parsebin).is_ok() && parsebin.sync_state_with_parent().is_ok() {
+                if let Some(sink) = parsebin.static_pad("sink") {
+                    let _ = pad.link(&sink);
+                }
+            }
+        });
+
+        let ring = Ring::new(time_shift.max_size);
+        let sample_ring = ring.clone();
+        let eos_ring = ring.clone();
+        appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let buffer = sample.buffer_owned().ok_or(gst::FlowError::Error)?;
+                    sample_ring.push(Chunk {
+                        time: appsink
+                            .current_running_time()
+                            .unwrap_or(gst::ClockTime::ZERO),
+                        keyframe: !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT),
+                        buffer,
+                    });
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .eos(move |_| eos_ring.end())
+                .build(),
+        );
+
+        pipeline
+            .set_state(gst::State::Playing)
+            .map_err(|e| PlayerError::state_change(format!("Failed to record: {}", e)))?;
+        Ok(Recorder {
+            pipeline,
+            ring,
+            network: is_network_uri(uri),
+            started: Instant::now(),
+        })
+    }
+
+    /// Gets the recording
+    pub(crate) fn ring(&self) -> &Ring {
+        &self.ring
+    }
+
+    /// Checks if recording has not reached a keyframe in reasonable time, e.g. because
+    /// none of the streams can be carried in MPEG-TS
+    pub(crate) fn is_stalled(&self) -> bool {
+        self.started.elapsed() > START_TIMEOUT && self.ring.window().is_none()
+    }
+
+    /// Takes the error that stopped recording, if any
+    pub(crate) fn take_error(&self) -> Option<PlayerError> {
+        let msg = self
+            .pipeline
+            .bus()?
+            .pop_filtered(&[gst::MessageType::Error])?;
+        let gst::MessageView::Error(err) = msg.view() else {
+            return None;
+        };
+        Some(PlayerError::from_glib(
+            &err.error(),
+            err.src().map(|s| s.path_string().to_string()),
+            err.debug().map(|d| d.to_string()),
+            self.network,
+        ))
+    }
+}
+
+/// Set state to NULL on drop to stop recording
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+    }
+}
+
+/// Links a parsed stream to the muxer, or drops it when MPEG-TS cannot carry it
+fn link_stream(pipeline: &gst::Pipeline, mux: &gst::Element, pad: &gst::Pad) {
+    if let Some(sink) = mux.request_pad_simple("sink_%d") {
+        if pad.link(&sink).is_ok() {
+            return;
+        }
+        mux.release_request_pad(&sink);
+    }
+    let Ok(fakesink) = gst::ElementFactory::make("fakesink")
+        .property("sync", false)
+        .property("async", false)
+        .build()
+    else {
+        return;
+    };
+    if pipeline.add(&fakesink).is_ok() && fakesink.sync_state_with_parent().is_ok() {
+        if let Some(sink) = fakesink.static_pad("sink") {
+            let _ = pad.link(&sink);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records one chunk of 100 bytes per second, a keyframe every `gop` seconds
+    fn record(ring: &Ring, seconds: u64, gop: u64) {
+        for second in 0..seconds {
+            ring.push(Chunk {
+                time: gst::ClockTime::from_seconds(second),
+                keyframe: second % gop == 0,
+                buffer: gst::Buffer::from_mut_slice(vec![0u8; 100]),
+            });
+        }
+    }
+
+    #[test]
+    fn window_is_what_the_ring_holds() {
+        gst::init().unwrap();
+        // Room for 10 chunks out of 30, the oldest kept keyframe starts the window
+        let ring = Ring::new(1000);
+        record(&ring, 30, 3);
+        let seconds = gst::ClockTime::from_seconds;
+        assert_eq!(ring.window(), Some((seconds(21), seconds(29))));
+        assert_eq!(ring.0.state.lock().unwrap().chunks.len(), 10);
+    }
+
+    #[test]
+    fn rewinds_to_keyframes() {
+        gst::init().unwrap();
+        let ring = Ring::new(u64::MAX);
+        record(&ring, 20, 5);
+        let seconds = gst::ClockTime::from_seconds;
+        assert_eq!(ring.rewind_to(seconds(13)), Some(seconds(10)));
+        assert_eq!(ring.0.state.lock().unwrap().cursor, 10);
+        assert_eq!(ring.rewind_to(seconds(19)), Some(seconds(15)));
+        assert_eq!(
+            ring.rewind_to(gst::ClockTime::ZERO),
+            Some(gst::ClockTime::ZERO)
+        );
+    }
+}
@@ -0,0 +1,613 @@
+use eframe::egui;
+use egui::TextureHandle;
+use gstreamer as gst;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::error::PlayerError;
+use crate::location::MediaLocation;
+use crate::player::{EosAction, MediaPlayer, ReverseSupport, SeekMode, PLAYBACK_RATES};
+
+/// Height reserved at the bottom of the widget for the control bar
+const CONTROLS_HEIGHT: f32 = 28.0;
+
+/// How often the position display is refreshed while playing media without video
+const AUDIO_ONLY_REPAINT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How the video is scaled into the space given to the widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// Scale to fit entirely inside the widget, keeping the aspect ratio (letterboxing)
+    #[default]
+    Contain,
+    /// Scale to cover the whole widget, keeping the aspect ratio (cropping)
+    Cover,
+    /// Stretch to the widget size, ignoring the aspect ratio
+    Fill,
+    /// Draw at the native video size, centered
+    Original,
+}
+
+impl FitMode {
+    /// Computes where a video of `video_size` is drawn inside `area`
+    pub fn layout(self, video_size: egui::Vec2, area: egui::Rect) -> egui::Rect {
+        let size = match self {
+            FitMode::Contain => {
+                video_size * (area.width() / video_size.x).min(area.height() / video_size.y)
+            }
+            FitMode::Cover => {
+                video_size * (area.width() / video_size.x).max(area.height() / video_size.y)
+            }
+            FitMode::Fill => area.size(),
+            FitMode::Original => video_size,
+        };
+        egui::Rect::from_center_size(area.center(), size)
+    }
+}
+
+/// The aspect ratio the video is displayed at
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AspectRatio {
+    /// Use the display aspect ratio of the stream, including its pixel aspect ratio
+    #[default]
+    Auto,
+    /// Force a fixed width / height ratio, e.g. 16.0 / 9.0
+    Fixed(f32),
+}
+
+impl AspectRatio {
+    pub const RATIO_4_3: AspectRatio = AspectRatio::Fixed(4.0 / 3.0);
+    pub const RATIO_16_9: AspectRatio = AspectRatio::Fixed(16.0 / 9.0);
+    pub const RATIO_2_35_1: AspectRatio = AspectRatio::Fixed(2.35);
+
+    /// Computes the size to display a frame of `frame_size` at, given the stream's own aspect ratio
+    pub fn display_size(self, frame_size: egui::Vec2, stream_aspect: f32) -> egui::Vec2 {
+        let aspect = match self {
+            AspectRatio::Auto => stream_aspect,
+            AspectRatio::Fixed(aspect) => aspect,
+        };
+        // Keep the frame height and derive the width, so only one axis is resampled
+        egui::vec2(frame_size.y * aspect, frame_size.y)
+    }
+}
+
+/// Options controlling the behaviour of a [`VideoPlayer`]
+#[derive(Debug, Clone)]
+pub struct VideoPlayerOptions {
+    pub autoplay: bool,        // Start playing as soon as media is opened
+    pub eos_action: EosAction, // What happens at the end of the media
+    pub muted: bool,           // Mute audio output
+    pub controls: bool,        // Draw the control bar below the video
+    pub fit: FitMode,          // How the video is scaled into the widget
+    pub aspect: AspectRatio,   // Aspect ratio the video is displayed at
+}
+
+impl Default for VideoPlayerOptions {
+    fn default() -> Self {
+        VideoPlayerOptions {
+            autoplay: true,
+            eos_action: EosAction::default(),
+            muted: false,
+            controls: true,
+            fit: FitMode::default(),
+            aspect: AspectRatio::default(),
+        }
+    }
+}
+
+/// An embeddable video player: a [`MediaPlayer`] plus the state needed to draw it with egui.
+///
+/// Add it to a `Ui` with `ui.add(&mut video_player)`.
+pub struct VideoPlayer {
+    player: MediaPlayer,
+    options: VideoPlayerOptions,
+    texture: Option<TextureHandle>, // Egui texture for displaying the video
+    texture_sequence: u64,          // Sequence number of the frame in the texture
+    stream_aspect: f32,             // Display aspect ratio of the frame in the texture
+}
+
+impl VideoPlayer {
+    /// Creates a new VideoPlayer with default options
+    pub fn new() -> Result<Self, PlayerError> {
+        Self::with_options(VideoPlayerOptions::default())
+    }
+
+    /// Creates a new VideoPlayer with the given options
+    pub fn with_options(options: VideoPlayerOptions) -> Result<Self, PlayerError> {
+        let mut video_player = VideoPlayer {
+            player: MediaPlayer::new()?,
+            options: VideoPlayerOptions::default(),
+            texture: None,
+            texture_sequence: 0,
+            stream_aspect: 1.0,
+        };
+        video_player.set_options(options);
+        Ok(video_player)
+    }
+
+    /// Gets the underlying media player
+    pub fn player(&self) -> &MediaPlayer {
+        &self.player
+    }
+
+    /// Gets the underlying media player mutably
+    pub fn player_mut(&mut self) -> &mut MediaPlayer {
+        &mut self.player
+    }
+
+    /// Gets the current options
+    pub fn options(&self) -> &VideoPlayerOptions {
+        &self.options
+    }
+
+    /// Replaces the current options, applying them to the media player
+    pub fn set_options(&mut self, options: VideoPlayerOptions) {
+        if let Err(e) = self.player.set_eos_action(options.eos_action) {
+            self.player.report_error(e);
+        }
+        self.player.set_muted(options.muted);
+        self.options = options;
+    }
+
+    /// Shows or hides the control bar
+    pub fn set_controls(&mut self, controls: bool) {
+        self.options.controls = controls;
+    }
+
+    /// Sets how the video is scaled into the widget
+    pub fn set_fit(&mut self, fit: FitMode) {
+        self.options.fit = fit;
+    }
+
+    /// Sets the aspect ratio the video is displayed at
+    pub fn set_aspect(&mut self, aspect: AspectRatio) {
+        self.options.aspect = aspect;
+    }
+
+    /// Sets what happens when the end of the media is reached
+    pub fn set_eos_action(&mut self, action: EosAction) -> Result<(), PlayerError> {
+        self.player.set_eos_action(action)?;
+        self.options.eos_action = action;
+        Ok(())
+    }
+
+    /// Opens a video file, starting playback if autoplay is enabled
+    pub fn open(&mut self, path: PathBuf) -> Result<(), PlayerError> {
+        self.player.load_file(path)?;
+        self.opened()
+    }
+
+    /// Opens media from a URI, starting playback if autoplay is enabled
+    pub fn open_uri(&mut self, uri: &str) -> Result<(), PlayerError> {
+        self.player.load_uri(uri)?;
+        self.opened()
+    }
+
+    /// Opens media from a file or URI, starting playback if autoplay is enabled
+    pub fn open_location(&mut self, location: &MediaLocation) -> Result<(), PlayerError> {
+        self.player.load(location)?;
+        self.opened()
+    }
+
+    /// Drops the picture of the previous media and starts playback if autoplay is enabled
+    fn opened(&mut self) -> Result<(), PlayerError> {
+        self.texture = None;
+        self.texture_sequence = 0;
+        if self.options.autoplay {
+            self.player.play()?;
+        }
+        Ok(())
+    }
+
+    /// Checks if a video frame is available for display
+    pub fn has_frame(&self) -> bool {
+        self.texture.is_some()
+    }
+
+    /// Updates the Egui texture with the current video frame.
+    /// Only frames with a new sequence number are uploaded, and they reuse the existing texture.
+    fn update_texture(&mut self, ctx: &egui::Context) {
+        if self.player.with_frame(|frame| frame.is_none()) {
+            self.texture = None;
+            self.texture_sequence = 0;
+            return;
+        }
+        // The pixels are moved out under the lock, the image is built without it
+        let Some(frame) = self.player.take_frame(self.texture_sequence) else {
+            return;
+        };
+        let size = [frame.width as usize, frame.height as usize];
+        if frame.pixels.len() != size[0] * size[1] {
+            return;
+        }
+
+        self.texture_sequence = frame.sequence;
+        self.stream_aspect = frame.display_aspect_ratio();
+        let image = egui::ColorImage {
+            size,
+            pixels: frame.pixels,
+        };
+        match &mut self.texture {
+            Some(texture) => texture.set(image, egui::TextureOptions::LINEAR),
+            None => {
+                self.texture =
+                    Some(ctx.load_texture("video-frame", image, egui::TextureOptions::LINEAR))
+            }
+        }
+    }
+
+    /// Toggles fullscreen mode of the viewport the widget is shown in
+    fn toggle_fullscreen(&mut self, ctx: &egui::Context) {
+        let is_fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!is_fullscreen));
+    }
+
+    /// Gets the range the position slider covers, the whole media or the seekable
+    /// window of a stream without a duration
+    fn seekable_range(&self) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        match self.player.duration() {
+            Some(duration) => Some((gst::ClockTime::ZERO, duration)),
+            None => self.player.seek_window(),
+        }
+    }
+
+    /// Draws the control bar with playback controls
+    fn controls_ui(&mut self, ui: &mut egui::Ui) {
+        // Determine the play/pause button text based on current state
+        let play_button_text = match self.player.get_state() {
+            gst::State::Playing => "⏸",
+            _ => "⏵",
+        };
+
+        let is_live = self.player.is_live();
+        let time_shifting = self.player.is_time_shifting();
+        let seekable_range = self.seekable_range();
+        ui.horizontal(|ui| {
+            // Play/Pause and Stop buttons
+            if ui.button(play_button_text).clicked() {
+                if let Err(e) = self.player.toggle_playback() {
+                    self.player.report_error(e);
+                }
+            }
+            if ui.button("⏹").clicked() {
+                if let Err(e) = self.player.stop() {
+                    self.player.report_error(e);
+                }
+            }
+
+            // Playback rate and direction, live streams only play at normal speed
+            ui.add_enabled_ui(!is_live, |ui| {
+                ui.menu_button(format!("{}×", self.player.rate()), |ui| {
+                    let rate = self.player.rate();
+                    for speed in PLAYBACK_RATES {
+                        if ui
+                            .selectable_label(rate.abs() == speed, format!("{}×", speed))
+                            .clicked()
+                        {
+                            if let Err(e) = self.player.set_rate(speed.copysign(rate)) {
+                                self.player.report_error(e);
+                            }
+                            ui.close_menu();
+                        }
+                    }
+                    ui.separator();
+                    let support = self.player.reverse_support();
+                    let mut reverse = self.player.is_reverse();
+                    let response = ui.add_enabled(
+                        support != ReverseSupport::Unsupported,
+                        egui::Checkbox::new(&mut reverse, "Reverse"),
+                    );
+                    let response = match support {
+                        ReverseSupport::Unsupported => {
+                            response.on_disabled_hover_text("This media cannot be played backwards")
+                        }
+                        ReverseSupport::KeyframesOnly => response
+                            .on_hover_text("Only keyframes are shown when playing backwards"),
+                        _ => response,
+                    };
+                    if response.changed() {
+                        if let Err(e) = self.player.set_reverse(reverse) {
+                            self.player.report_error(e);
+                        }
+                        ui.close_menu();
+                    }
+                });
+
+                // A-B loop markers
+                let (loop_a, loop_b) = self.player.loop_markers();
+                if ui
+                    .selectable_label(loop_a.is_some(), "A")
+                    .on_hover_text("Set loop start")
+                    .clicked()
+                {
+                    if let Err(e) = self.player.set_loop_a() {
+                        self.player.report_error(e);
+                    }
+                }
+                if ui
+                    .selectable_label(loop_b.is_some(), "B")
+                    .on_hover_text("Set loop end")
+                    .clicked()
+                {
+                    if let Err(e) = self.player.set_loop_b() {
+                        self.player.report_error(e);
+                    }
+                }
+                if (loop_a.is_some() || loop_b.is_some())
+                    && ui.button("✖").on_hover_text("Clear loop").clicked()
+                {
+                    if let Err(e) = self.player.clear_loop() {
+                        self.player.report_error(e);
+                    }
+                }
+            });
+
+            // Position slider, seeking to keyframes while dragging and accurately on release
+            ui.style_mut().spacing.slider_width = ui.available_width() - 300.0;
+            if is_live && !time_shifting {
+                // Live streams always play at the live edge
+                let mut pos = 1.0;
+                ui.add_enabled(
+                    false,
+                    egui::Slider::new(&mut pos, 0.0..=1.0).show_value(false),
+                )
+                .on_disabled_hover_text(
+                    "Live streams can only be seeked with the time-shift buffer",
+                );
+            } else if let (Some(position), Some((start, end))) =
+                (self.player.position(), seekable_range)
+            {
+                // Streams kept in the time-shift recording seek within their window
+                let length = end.saturating_sub(start);
+                let mut pos = position.saturating_sub(start).nseconds() as f64
+                    / length.nseconds().max(1) as f64;
+                let response = ui.add(egui::Slider::new(&mut pos, 0.0..=1.0).show_value(false));
+                if let Some(duration) = self.player.duration() {
+                    let (loop_a, loop_b) = self.player.loop_markers();
+                    paint_loop_markers(ui, response.rect, duration, loop_a, loop_b);
+                }
+                let mode = if response.drag_stopped() {
+                    Some(SeekMode::Accurate)
+                } else if response.dragged() && response.changed() {
+                    Some(SeekMode::Keyframe)
+                } else if response.changed() {
+                    Some(self.player.seek_mode())
+                } else {
+                    None
+                };
+                if let Some(mode) = mode {
+                    if let Some(target) = self.player.fraction_to_time(pos) {
+                        if let Err(e) = self.player.seek_to(target, mode) {
+                            self.player.report_error(e);
+                        }
+                    }
+                }
+            } else {
+                let mut pos = 0.0;
+                ui.add(
+                    egui::Slider::new(&mut pos, 0.0..=1.0)
+                        .step_by(0.0)
+                        .show_value(false),
+                );
+            }
+
+            // Time display and volume controls
+            ui.horizontal(|ui| {
+                ui.set_width(300.0);
+                if is_live && !time_shifting {
+                    ui.label(
+                        egui::RichText::new(" LIVE ")
+                            .strong()
+                            .color(egui::Color32::WHITE)
+                            .background_color(egui::Color32::from_rgb(200, 30, 30)),
+                    )
+                    .on_hover_text("Live stream");
+                    if let Some(position) = self.player.position() {
+                        ui.label(format!(
+                            "{:02}:{:02}",
+                            position.seconds() / 60,
+                            position.seconds() % 60
+                        ));
+                    }
+                } else if let (Some(position), Some((_, end))) =
+                    (self.player.position(), self.player.seek_window())
+                {
+                    // Red while at the live edge, otherwise a button to catch up
+                    let behind = self.player.is_behind_live();
+                    let fill = if behind {
+                        ui.visuals().widgets.inactive.weak_bg_fill
+                    } else {
+                        egui::Color32::from_rgb(200, 30, 30)
+                    };
+                    let response = ui
+                        .add(egui::Button::new(egui::RichText::new("LIVE").strong()).fill(fill))
+                        .on_hover_text(if behind { "Go to live" } else { "Playing live" });
+                    if response.clicked() && behind {
+                        if let Err(e) = self.player.go_to_live() {
+                            self.player.report_error(e);
+                        }
+                    }
+                    if behind {
+                        let delay = end.saturating_sub(position);
+                        ui.label(format!(
+                            "-{:02}:{:02}",
+                            delay.seconds() / 60,
+                            delay.seconds() % 60
+                        ))
+                        .on_hover_text("Time behind live");
+                    }
+                } else if let (Some(position), Some(duration)) =
+                    (self.player.position(), self.player.duration())
+                {
+                    ui.label(format!(
+                        "{:02}:{:02} / {:02}:{:02}",
+                        position.seconds() / 60,
+                        position.seconds() % 60,
+                        duration.seconds() / 60,
+                        duration.seconds() % 60
+                    ));
+                } else {
+                    ui.label("00:00 / 00:00");
+                }
+                if let Some(frame_number) = self.player.frame_number() {
+                    ui.label(format!("#{}", frame_number))
+                        .on_hover_text("Current frame");
+                }
+                ui.separator();
+                let mut muted = self.player.is_muted();
+                if ui.selectable_label(muted, "🔊").clicked() {
+                    muted = !muted;
+                    self.options.muted = muted;
+                    self.player.set_muted(muted);
+                }
+                let mut volume = self.player.volume();
+                ui.style_mut().spacing.slider_width = ui.available_width() - 36.0;
+                if ui
+                    .add(egui::Slider::new(&mut volume, 0.0..=1.0).show_value(false))
+                    .changed()
+                {
+                    self.player.set_volume(volume);
+                }
+                if ui.button("🗖").clicked() {
+                    self.toggle_fullscreen(ui.ctx());
+                }
+            });
+        });
+    }
+}
+
+/// Draws the A-B loop markers and the looped range over a position slider
+fn paint_loop_markers(
+    ui: &egui::Ui,
+    rect: egui::Rect,
+    duration: gst::ClockTime,
+    a: Option<gst::ClockTime>,
+    b: Option<gst::ClockTime>,
+) {
+    // Same geometry as egui's slider, which keeps the handle inside the rect
+    let rail = rect.x_range().shrink(rect.height() / 2.5);
+    let x = |time: gst::ClockTime| {
+        rail.min + rail.span() * (time.nseconds() as f32 / duration.nseconds().max(1) as f32)
+    };
+    let color = ui.visuals().selection.stroke.color;
+    let painter = ui.painter();
+
+    if let (Some(a), Some(b)) = (a, b) {
+        let y = rect.center().y;
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(x(a)..=x(b), (y - 2.0)..=(y + 2.0)),
+            0.0,
+            color.gamma_multiply(0.6),
+        );
+    }
+    for marker in [a, b].into_iter().flatten() {
+        let x = x(marker);
+        painter.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            egui::Stroke::new(2.0, color),
+        );
+    }
+}
+
+impl egui::Widget for &mut VideoPlayer {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        // Keep our state updated
+        self.player.set_repaint_context(ui.ctx());
+        self.player.update();
+        self.update_texture(ui.ctx());
+
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click());
+
+        let mut video_rect = rect;
+        if self.options.controls {
+            video_rect.max.y = (rect.max.y - CONTROLS_HEIGHT).max(rect.min.y);
+        }
+
+        // Display the video frame, scaled according to the aspect ratio and fit mode
+        if let Some(texture) = &self.texture {
+            let painter = ui.painter().with_clip_rect(video_rect);
+            painter.rect_filled(video_rect, 0.0, egui::Color32::BLACK);
+            painter.image(
+                texture.id(),
+                self.options.fit.layout(
+                    self.options
+                        .aspect
+                        .display_size(texture.size_vec2(), self.stream_aspect),
+                    video_rect,
+                ),
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+
+        // Name the missing plugins, the video would otherwise just stay black
+        if !self.player.missing_plugins().is_empty() {
+            let mut notice_ui = ui.new_child(
+                egui::UiBuilder::new()
+                    .max_rect(video_rect.shrink(16.0))
+                    .layout(egui::Layout::top_down(egui::Align::Center)),
+            );
+            egui::Frame::popup(notice_ui.style()).show(&mut notice_ui, |ui| {
+                for missing in self.player.missing_plugins() {
+                    ui.label(
+                        egui::RichText::new(format!("Missing plugin: {}", missing.description))
+                            .strong(),
+                    );
+                    if let Some(plugin_set) = missing.plugin_set() {
+                        ui.label(plugin_set.install_hint());
+                    }
+                }
+            });
+        }
+
+        // Tell the user why a stream is not moving yet
+        let status = match self.player.pending_retry() {
+            Some((attempt, _)) => Some(format!(
+                "Reconnecting, attempt {} of {}",
+                attempt,
+                self.player.retry_policy().max_attempts
+            )),
+            None => self
+                .player
+                .buffering_percent()
+                .map(|percent| format!("Buffering {}%", percent)),
+        };
+        if let Some(status) = status {
+            let painter = ui.painter().with_clip_rect(video_rect);
+            let galley = painter.layout_no_wrap(
+                status,
+                egui::FontId::proportional(18.0),
+                egui::Color32::WHITE,
+            );
+            let text_rect =
+                egui::Align2::CENTER_CENTER.anchor_size(video_rect.center(), galley.size());
+            painter.rect_filled(
+                text_rect.expand(8.0),
+                6.0,
+                egui::Color32::from_black_alpha(160),
+            );
+            painter.galley(text_rect.min, galley, egui::Color32::WHITE);
+        }
+
+        if self.options.controls {
+            let controls_rect =
+                egui::Rect::from_min_max(egui::pos2(rect.min.x, video_rect.max.y), rect.max);
+            ui.painter()
+                .rect_filled(controls_rect, 0.0, ui.visuals().panel_fill);
+            let mut controls_ui = ui.new_child(
+                egui::UiBuilder::new()
+                    .max_rect(controls_rect.shrink2(egui::vec2(8.0, 3.0)))
+                    .layout(egui::Layout::left_to_right(egui::Align::Center)),
+            );
+            self.controls_ui(&mut controls_ui);
+        }
+
+        // New frames and bus messages wake the UI up on their own. Without
+        // video there are no frames, so keep the position display moving.
+        if self.player.get_state() == gst::State::Playing && self.texture.is_none() {
+            ui.ctx().request_repaint_after(AUDIO_ONLY_REPAINT_INTERVAL);
+        }
+
+        response
+    }
+}
@@ -0,0 +1,80 @@
+//! Helpers shared by the integration tests
+
+#![allow(dead_code)]
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use videotest::{MediaPlayer, PlayerEvent};
+
+/// Serializes the tests of one binary. Players and servers all run on the default
+/// main context, which only one thread at a time can iterate.
+pub fn serial() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Checks that the elements a test needs are installed, so tests can be skipped on
+/// systems without them instead of failing
+pub fn has_elements(names: &[&str]) -> bool {
+    gst::init().unwrap();
+    let missing: Vec<_> = names
+        .iter()
+        .filter(|name| gst::ElementFactory::find(name).is_none())
+        .collect();
+    if !missing.is_empty() {
+        eprintln!("Skipping, missing GStreamer elements: {:?}", missing);
+    }
+    missing.is_empty()
+}
+
+/// Runs a pipeline description to the end, e.g. to write a test clip
+pub fn run_to_eos(description: &str) {
+    let pipeline = gst::parse::launch(description).unwrap();
+    let bus = pipeline.bus().unwrap();
+    pipeline.set_state(gst::State::Playing).unwrap();
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(30),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        )
+        .expect("pipeline did not finish");
+    pipeline.set_state(gst::State::Null).unwrap();
+    if let gst::MessageView::Error(err) = msg.view() {
+        panic!("{} failed: {}", description, err.error());
+    }
+}
+
+/// Writes a five second Motion JPEG clip, a format every GStreamer install can play
+pub fn write_test_clip(path: &Path) {
+    run_to_eos(&format!(
+        "videotestsrc num-buffers=150 \
+         ! video/x-raw,width=320,height=240,framerate=30/1 \
+         ! jpegenc ! avimux ! filesink location={}",
+        path.display()
+    ));
+}
+
+/// Updates the player until `done` returns true for it, handing every event to
+/// `on_event`. Fails the test on timeout.
+pub fn run_until(
+    player: &mut MediaPlayer,
+    timeout: Duration,
+    mut on_event: impl FnMut(&PlayerEvent),
+    mut done: impl FnMut(&MediaPlayer) -> bool,
+) {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        player.update();
+        for event in player.poll_events() {
+            on_event(&event);
+        }
+        if done(player) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("timed out after {:?}", timeout);
+}
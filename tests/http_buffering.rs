@@ -0,0 +1,178 @@
+//! Plays clips from local HTTP servers and checks buffering and reconnecting are
+//! reported
+
+mod common;
+
+use std::cell::{Cell, RefCell};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use videotest::{MediaPlayer, PlayerError, PlayerEvent, RetryPolicy};
+
+/// Bytes sent at once, and the pause after each send, so the clip arrives over seconds
+const CHUNK_SIZE: usize = 16 * 1024;
+const CHUNK_DELAY: Duration = Duration::from_millis(20);
+
+/// Answers any GET request with the clip, sent slowly
+fn serve(mut stream: TcpStream, clip: &[u8]) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: video/x-msvideo\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        clip.len()
+    )?;
+    for chunk in clip.chunks(CHUNK_SIZE) {
+        stream.write_all(chunk)?;
+        thread::sleep(CHUNK_DELAY);
+    }
+    Ok(())
+}
+
+/// Starts the server on a free port and gets the port
+fn start_server(clip: Vec<u8>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let clip = Arc::new(clip);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let clip = Arc::clone(&clip);
+            // The player may give up on a connection, that is not an error here
+            thread::spawn(move || serve(stream, &clip));
+        }
+    });
+    port
+}
+
+#[test]
+fn buffering_is_reported_for_http_streams() {
+    let elements = [
+        "videotestsrc",
+        "jpegenc",
+        "jpegdec",
+        "avimux",
+        "avidemux",
+        "souphttpsrc",
+    ];
+    if !common::has_elements(&elements) {
+        return;
+    }
+    let _serial = common::serial();
+    let path = std::env::temp_dir().join(format!("videotest-buffering-{}.avi", std::process::id()));
+    common::write_test_clip(&path);
+    let clip = std::fs::read(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let port = start_server(clip);
+
+    let mut player = MediaPlayer::new().unwrap();
+    player
+        .load_uri(&format!("http://127.0.0.1:{}/clip.avi", port))
+        .unwrap();
+    player.play().unwrap();
+
+    let mut buffering_events = 0;
+    let mut was_buffering = false;
+    common::run_until(
+        &mut player,
+        Duration::from_secs(30),
+        |event| match event {
+            PlayerEvent::Buffering(_) => buffering_events += 1,
+            PlayerEvent::Error(error) => panic!("playback failed: {}", error),
+            _ => {}
+        },
+        |player| {
+            // Done once the buffer was seen filling and then full again
+            match player.buffering_percent() {
+                Some(percent) => {
+                    assert!((0..100).contains(&percent));
+                    was_buffering = true;
+                    false
+                }
+                None => was_buffering,
+            }
+        },
+    );
+    assert!(buffering_events > 0);
+    assert!(!player.is_live());
+}
+
+/// Starts a server that hangs up on every request without answering and gets its port
+fn start_failing_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut line = String::new();
+            let _ = BufReader::new(stream).read_line(&mut line);
+        }
+    });
+    port
+}
+
+#[test]
+fn failed_streams_are_reopened_until_the_attempts_are_used_up() {
+    if !common::has_elements(&["souphttpsrc"]) {
+        return;
+    }
+    let _serial = common::serial();
+    let port = start_failing_server();
+
+    let mut player = MediaPlayer::new().unwrap();
+    player.set_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(200),
+        multiplier: 2.0,
+    });
+    player
+        .load_uri(&format!("http://127.0.0.1:{}/clip.avi", port))
+        .unwrap();
+    player.play().unwrap();
+
+    // Every attempt fails again, the last failure is final
+    let attempts = RefCell::new(Vec::new());
+    let gave_up = Cell::new(false);
+    common::run_until(
+        &mut player,
+        Duration::from_secs(30),
+        |event| match event {
+            PlayerEvent::Reconnecting {
+                attempt,
+                max_attempts,
+            } => {
+                assert_eq!(*max_attempts, 3);
+                attempts.borrow_mut().push(*attempt);
+            }
+            // The source's failure may be followed by generic stream errors
+            PlayerEvent::Error(PlayerError::NetworkFailure(_)) => {
+                gave_up.set(attempts.borrow().len() == 3);
+            }
+            _ => {}
+        },
+        |player| {
+            if let Some((attempt, delay)) = player.pending_retry() {
+                assert_eq!(attempt as usize, attempts.borrow().len() + 1);
+                assert!(delay <= Duration::from_millis(200));
+            }
+            gave_up.get()
+        },
+    );
+    assert_eq!(*attempts.borrow(), [1, 2, 3]);
+    assert!(player.pending_retry().is_none());
+
+    // Nothing is tried after the last attempt
+    for _ in 0..50 {
+        player.update();
+        for event in player.poll_events() {
+            assert!(!matches!(event, PlayerEvent::Reconnecting { .. }));
+        }
+        assert!(player.pending_retry().is_none());
+        thread::sleep(Duration::from_millis(10));
+    }
+}
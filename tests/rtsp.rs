@@ -0,0 +1,189 @@
+//! Plays streams from a local RTSP server with the RTSP settings applied
+
+mod common;
+
+use gstreamer as gst;
+use gstreamer_rtsp_server as gst_rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use std::cell::Cell;
+use std::time::Duration;
+use videotest::{MediaPlayer, PlayerError, PlayerEvent, RtspSettings, RtspTransport};
+
+/// Elements needed on both ends of the stream
+const ELEMENTS: [&str; 6] = [
+    "videotestsrc",
+    "jpegenc",
+    "rtpjpegpay",
+    "rtspsrc",
+    "rtpjpegdepay",
+    "jpegdec",
+];
+
+/// Pipeline the server streams, a live Motion JPEG test pattern
+const LAUNCH: &str = "( videotestsrc is-live=true \
+                      ! video/x-raw,width=64,height=48,framerate=15/1 \
+                      ! jpegenc ! rtpjpegpay name=pay0 pt=26 )";
+
+/// A server on a free port of the loopback interface, serving `/test`
+struct Server {
+    server: gst_rtsp_server::RTSPServer,
+    _source: glib::SourceId,
+}
+
+impl Server {
+    /// Starts the server, requiring the given user name and password when there are some
+    fn start(credentials: Option<(&str, &str)>) -> Self {
+        let server = gst_rtsp_server::RTSPServer::new();
+        server.set_address("127.0.0.1");
+        server.set_service("0");
+
+        let factory = gst_rtsp_server::RTSPMediaFactory::new();
+        factory.set_launch(LAUNCH);
+        factory.set_shared(true);
+
+        if let Some((user, password)) = credentials {
+            let auth = gst_rtsp_server::RTSPAuth::new();
+            let token = gst_rtsp_server::RTSPToken::new(&[(
+                gst_rtsp_server::RTSP_TOKEN_MEDIA_FACTORY_ROLE,
+                &"user",
+            )]);
+            let basic = gst_rtsp_server::RTSPAuth::make_basic(user, password);
+            auth.add_basic(basic.as_str(), &token);
+            server.set_auth(Some(&auth));
+            factory.add_role_from_structure(
+                &gst::Structure::builder("user")
+                    .field(gst_rtsp_server::RTSP_PERM_MEDIA_FACTORY_ACCESS, true)
+                    .field(gst_rtsp_server::RTSP_PERM_MEDIA_FACTORY_CONSTRUCT, true)
+                    .build(),
+            );
+        }
+
+        server.mount_points().unwrap().add_factory("/test", factory);
+        // The server runs on the default main context, which the player iterates
+        let source = server.attach(None).unwrap();
+        Server {
+            server,
+            _source: source,
+        }
+    }
+
+    /// Gets the URI of the test stream
+    fn uri(&self) -> String {
+        format!("rtsp://127.0.0.1:{}/test", self.server.bound_port())
+    }
+}
+
+/// Opens the server's stream with the given settings and starts playing it
+fn open(server: &Server, settings: RtspSettings) -> MediaPlayer {
+    let mut player = MediaPlayer::new().unwrap();
+    player.set_rtsp_settings(settings);
+    player.load_uri(&server.uri()).unwrap();
+    player.play().unwrap();
+    player
+}
+
+#[test]
+fn plays_over_tcp() {
+    let _serial = common::serial();
+    if !common::has_elements(&ELEMENTS) {
+        return;
+    }
+    let server = Server::start(None);
+    let mut player = open(
+        &server,
+        RtspSettings {
+            transport: RtspTransport::Tcp,
+            latency: gst::ClockTime::from_mseconds(200),
+            ..Default::default()
+        },
+    );
+
+    common::run_until(
+        &mut player,
+        Duration::from_secs(20),
+        |event| {
+            if let PlayerEvent::Error(error) = event {
+                panic!("playback failed: {}", error);
+            }
+        },
+        |player| player.with_frame(|frame| frame.is_some_and(|frame| frame.width == 64)),
+    );
+    assert!(player.is_live());
+    assert_eq!(player.duration(), None);
+}
+
+#[test]
+fn sends_credentials() {
+    let _serial = common::serial();
+    if !common::has_elements(&ELEMENTS) {
+        return;
+    }
+    let server = Server::start(Some(("viewer", "secret")));
+    let mut player = open(
+        &server,
+        RtspSettings {
+            transport: RtspTransport::Tcp,
+            user_id: Some("viewer".to_string()),
+            user_pw: Some("secret".to_string()),
+            ..Default::default()
+        },
+    );
+    common::run_until(
+        &mut player,
+        Duration::from_secs(20),
+        |event| {
+            if let PlayerEvent::Error(error) = event {
+                panic!("playback failed: {}", error);
+            }
+        },
+        |player| player.with_frame(|frame| frame.is_some()),
+    );
+}
+
+#[test]
+fn wrong_credentials_are_refused() {
+    let _serial = common::serial();
+    if !common::has_elements(&ELEMENTS) {
+        return;
+    }
+    let server = Server::start(Some(("viewer", "secret")));
+    let mut player = open(
+        &server,
+        RtspSettings {
+            transport: RtspTransport::Tcp,
+            user_id: Some("viewer".to_string()),
+            user_pw: Some("wrong".to_string()),
+            ..Default::default()
+        },
+    );
+    let refused = Cell::new(false);
+    common::run_until(
+        &mut player,
+        Duration::from_secs(20),
+        |event| {
+            if let PlayerEvent::Error(error) = event {
+                assert!(
+                    matches!(error, PlayerError::PermissionDenied(_)),
+                    "unexpected error: {:?}",
+                    error
+                );
+                refused.set(true);
+            }
+        },
+        |_| refused.get(),
+    );
+    // A refused stream is not opened again
+    assert_eq!(player.pending_retry(), None);
+}
+
+#[test]
+fn debug_output_hides_the_password() {
+    let settings = RtspSettings {
+        user_id: Some("viewer".to_string()),
+        user_pw: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let debug = format!("{:?}", settings);
+    assert!(debug.contains("viewer"));
+    assert!(!debug.contains("secret"));
+}